@@ -1,33 +1,161 @@
 use crossterm::event::KeyCode;
 use crate::file_manager::FileManagerAction;
-use crate::app::App;
+use crate::app::{App, FocusScreen};
 use crossterm::event::Event;
 use crossterm::event::KeyEvent;
 use crossterm::event::KeyEventKind;
 use crossterm::event::KeyModifiers;
+use crate::popup::Popup;
+use crate::preview::encoding::ENCODING_CHOICES;
+use crate::archive::{self, ArchiveKind, ConflictPolicy, CREATABLE_KINDS};
+use crate::popup::PathPrompt;
+use crate::transfer::TransferMode;
+use crate::utility::path::expand;
+
+// Number of lines scrolled in the preview by the page keys
+static PREVIEW_PAGE_STEP: isize = 20;
+// Depth of a recursive listing when it's started, changed with + and -
+static DEFAULT_WALK_DEPTH: usize = 8;
 impl App {
 
+    /// Handles the inputs when a popup is displayed over the files and preview sections
+    pub fn handle_popup_input(&mut self, event: Event) {
+        if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event {
+            if code == KeyCode::Esc {
+                self.popup = None;
+                return;
+            }
+            match &mut self.popup {
+                Some(Popup::Encoding{selected}) => {
+                    match code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            *selected = selected.checked_sub(1).unwrap_or(ENCODING_CHOICES.len() - 1);
+                        },
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            *selected = (*selected + 1) % ENCODING_CHOICES.len();
+                        },
+                        KeyCode::Enter => {
+                            let encoding = ENCODING_CHOICES[*selected];
+                            self.popup = None;
+                            let _ = self.tab.file_manager.dispatch(FileManagerAction::ForceEncoding(encoding));
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::Extract{name, policy}) => {
+                    match code {
+                        KeyCode::Char(c) => name.push(c),
+                        KeyCode::Backspace => { name.pop(); },
+                        KeyCode::Tab => *policy = policy.next(),
+                        KeyCode::Enter => {
+                            let action = self.tab.list_state.selected().map(|index| FileManagerAction::Extract{index, folder: name.clone(), policy: *policy});
+                            self.popup = None;
+                            if let Some(action) = action {
+                                let _ = self.tab.file_manager.dispatch(action);
+                            }
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::Compress{name, kind, policy}) => {
+                    match code {
+                        KeyCode::Char(c) => name.push(c),
+                        KeyCode::Backspace => { name.pop(); },
+                        KeyCode::Tab => *policy = policy.next(),
+                        KeyCode::Up => *kind = kind.checked_sub(1).unwrap_or(CREATABLE_KINDS.len() - 1),
+                        KeyCode::Down => *kind = (*kind + 1) % CREATABLE_KINDS.len(),
+                        KeyCode::Enter => {
+                            let action = FileManagerAction::Compress{index: self.tab.list_state.selected(), name: name.clone(), kind: CREATABLE_KINDS[*kind], policy: *policy};
+                            self.popup = None;
+                            let _ = self.tab.file_manager.dispatch(action);
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::Transfer{mode, destination, policy}) => {
+                    match code {
+                        KeyCode::Char(c) => destination.push(c),
+                        KeyCode::Backspace => { destination.pop(); },
+                        KeyCode::Tab => *policy = policy.next(),
+                        KeyCode::Enter => {
+                            let action = FileManagerAction::Transfer{index: self.tab.list_state.selected(), destination: destination.clone(), mode: *mode, policy: *policy};
+                            self.popup = None;
+                            let _ = self.tab.file_manager.dispatch(action);
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::Bookmarks(_)) if code == KeyCode::Char('g') && modifiers.contains(KeyModifiers::CONTROL) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ImportGtkBookmarks);
+                    self.popup = Some(Popup::Bookmarks(self.bookmarks_picker()));
+                },
+                Some(Popup::Bookmarks(picker)) if code == KeyCode::Delete => {
+                    if let Some(index) = picker.selected_index() {
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::DeleteBookmark(index));
+                        self.popup = Some(Popup::Bookmarks(self.bookmarks_picker()));
+                    }
+                },
+                Some(Popup::Bookmarks(picker) | Popup::Jump(picker)) => {
+                    match code {
+                        KeyCode::Char(c) => picker.push(c),
+                        KeyCode::Backspace => picker.pop(),
+                        KeyCode::Up => picker.move_selection(-1),
+                        KeyCode::Down => picker.move_selection(1),
+                        KeyCode::Enter => {
+                            let path = picker.selected_item().map(|item| item.path.clone());
+                            self.popup = None;
+                            if let Some(path) = path {
+                                let _ = self.tab.file_manager.dispatch(FileManagerAction::OpenPath(path));
+                            }
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::GoTo(prompt)) => {
+                    match code {
+                        KeyCode::Char(c) => prompt.push(c),
+                        KeyCode::Backspace => prompt.pop(),
+                        KeyCode::Tab => prompt.complete(self.tab.file_manager.path()),
+                        // the popup stays open on an invalid path to let the user fix it
+                        KeyCode::Enter => {
+                            match expand(prompt.input(), self.tab.file_manager.path()) {
+                                Ok(path) if archive::folder_exists(&path) || path.is_file() => {
+                                    self.popup = None;
+                                    let _ = self.tab.file_manager.dispatch(FileManagerAction::OpenPath(path));
+                                },
+                                Ok(path) => prompt.set_error(format!("No such file or folder: {}", path.display())),
+                                Err(error) => prompt.set_error(error),
+                            }
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Popup::Create{..}) | None => {}
+            }
+        }
+    }
+
     /// Handles the inputs when the focus is on the files list with filter mode on
     pub fn handle_filter_input(&mut self, event: Event) {
         if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event {
             if modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('f') {
-                self.filter_mode = !self.filter_mode;
-                self.filter_buffer.clear();
-                self.list_state.select(self.min_selected());
-                let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.min_selected()));
+                self.tab.filter_mode = !self.tab.filter_mode;
+                self.tab.filter_buffer.clear();
+                self.tab.list_state.select(self.tab.min_selected());
+                let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.min_selected()));
                 return;
             }
             match code {
                 KeyCode::Up => {
-                    if self.filtered_files.is_empty() {
+                    if self.tab.filtered_files.is_empty() {
                         return;
                     }
-                    let mut selected = match self.list_state.selected() {
+                    let mut selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => {
-                            if let Some(min) = self.min_filtered_selected() {
-                                self.list_state.select(Some(min));
-                                let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[min])));
+                            if let Some(min) = self.tab.min_filtered_selected() {
+                                self.tab.list_state.select(Some(min));
+                                let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[min])));
                             }
                             return;
                         },
@@ -36,8 +164,8 @@ impl App {
                     if modifiers.contains(KeyModifiers::CONTROL) {
                         let (res, overflow) = selected.overflowing_sub(5);
                         if overflow {
-                            let overflow_len = (usize::MAX - res).strict_rem(self.filtered_files.len()) ;
-                            selected = res.clamp(0, self.filtered_files.len()- (1 + overflow_len));
+                            let overflow_len = (usize::MAX - res).strict_rem(self.tab.filtered_files.len()) ;
+                            selected = res.clamp(0, self.tab.filtered_files.len()- (1 + overflow_len));
                         }
                         else {
                             selected = res;
@@ -46,67 +174,67 @@ impl App {
                     else {
                         let (res, overflow) = selected.overflowing_sub(1);
                         if overflow {
-                            selected = self.filtered_files.len() - 1;
+                            selected = self.tab.filtered_files.len() - 1;
                         }
                         else {
                             selected = res;
                         }
                     }
-                    self.list_state.select(Some(selected));
-                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[selected])));
+                    self.tab.list_state.select(Some(selected));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[selected])));
                 },
                 KeyCode::Down => {
-                    if self.filtered_files.is_empty() {
+                    if self.tab.filtered_files.is_empty() {
                         return;
                     }
-                    let mut selected = match self.list_state.selected() {
+                    let mut selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => {
-                            if let Some(min) = self.min_filtered_selected() {
-                                self.list_state.select(Some(min));
-                                let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[min])));
+                            if let Some(min) = self.tab.min_filtered_selected() {
+                                self.tab.list_state.select(Some(min));
+                                let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[min])));
                             }
                             return;
                         },
                     };
                     // handle the selection 
                     if modifiers.contains(KeyModifiers::CONTROL) {
-                        selected = selected + 5;
-                        selected = selected.strict_rem(self.filtered_files.len());
+                        selected += 5;
+                        selected = selected.strict_rem(self.tab.filtered_files.len());
                     }
                     else {
-                        selected = selected + 1;
-                        selected = selected.strict_rem(self.filtered_files.len());
+                        selected += 1;
+                        selected = selected.strict_rem(self.tab.filtered_files.len());
                     }
-                    self.list_state.select(Some(selected));
-                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[selected])));
+                    self.tab.list_state.select(Some(selected));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[selected])));
                 },
                 KeyCode::Char(c) => {
                     // push the new character to the filter buffer then update the ui 
-                    self.filter_buffer.push(c);
-                    self.update_filtered_files();
-                    let min = self.min_filtered_selected();
-                    self.list_state.select(min);
+                    self.tab.filter_buffer.push(c);
+                    self.tab.update_filtered_files();
+                    let min = self.tab.min_filtered_selected();
+                    self.tab.list_state.select(min);
                     if let Some(min) = min {
-                        let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[min])));
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[min])));
                     }
                 },
                 KeyCode::Backspace => {
                     // pop the last character from the filter buffer then update the ui 
-                    self.filter_buffer.pop();
-                    self.update_filtered_files();
-                    let min = self.min_filtered_selected();
-                    self.list_state.select(min);
+                    self.tab.filter_buffer.pop();
+                    self.tab.update_filtered_files();
+                    let min = self.tab.min_filtered_selected();
+                    self.tab.list_state.select(min);
                     if let Some(min) = min {
-                        let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[min])));
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.tab.filtered_files[min])));
                     }
                 },
                 KeyCode::Enter => {
-                    let selected = match self.list_state.selected() {
+                    let selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => return,
                     };
-                    let _ = self.file_manager.dispatch(FileManagerAction::Open(self.filtered_files[selected]));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::Open(self.tab.filtered_files[selected]));
                 },
                 _ => {}
             }
@@ -114,27 +242,79 @@ impl App {
     }
 
 
+    /// Handles the inputs when the focus is on the preview
+    pub fn handle_preview_input(&mut self, event: Event) {
+        if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event {
+            // same steps as the files list
+            let step = if modifiers.contains(KeyModifiers::CONTROL) { 5 } else { 1 };
+            match code {
+                KeyCode::Tab | KeyCode::Esc => {
+                    self.focus = FocusScreen::Files;
+                },
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll(-step);
+                },
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll(step);
+                },
+                KeyCode::Left | KeyCode::Char('h') => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll_horizontally(-1);
+                },
+                KeyCode::Right | KeyCode::Char('l') => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll_horizontally(1);
+                },
+                KeyCode::PageUp => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll(-PREVIEW_PAGE_STEP);
+                },
+                KeyCode::PageDown => {
+                    self.tab.file_manager.selected_file_preview_mut().scroll(PREVIEW_PAGE_STEP);
+                },
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    self.tab.file_manager.selected_file_preview_mut().toggle();
+                },
+                KeyCode::Char('v') => {
+                    self.tab.file_manager.selected_file_preview_mut().cycle_view();
+                },
+                KeyCode::Char('f') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleFollow);
+                },
+                KeyCode::Char('e') if self.tab.file_manager.selected_file().is_some_and(|file| file.is_file()) => {
+                    self.popup = Some(Popup::Encoding{selected: 0});
+                },
+                _ => {}
+            }
+        }
+    }
+
     /// Handles the inputs when the focus is on the files list with filter mode off
     pub fn handle_files_input(&mut self, event: Event) {
         if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event {
+            // second key of a bookmark command, any other key cancels it
+            if let Some(prefix) = self.pending_key.take() {
+                if let KeyCode::Char(key) = code {
+                    let action = if prefix == 'm' { FileManagerAction::SetBookmark(key) } else { FileManagerAction::JumpToBookmark(key) };
+                    let _ = self.tab.file_manager.dispatch(action);
+                }
+                return;
+            }
             match code {
                 KeyCode::Up | KeyCode::Char('k') => {
-                    if self.file_manager.files().is_empty() {
+                    if self.tab.file_manager.files().is_empty() {
                         return;
                     }
-                    let mut selected = match self.list_state.selected() {
+                    let mut selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => {
-                            self.list_state.select(self.min_selected());
-                            let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.min_selected()));
+                            self.tab.list_state.select(self.tab.min_selected());
+                            let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.min_selected()));
                             return;
                         },
                     };
                     if modifiers.contains(KeyModifiers::CONTROL) {
                         let (res, overflow) = selected.overflowing_sub(5);
                         if overflow {
-                            let overflow_len = (usize::MAX - res).strict_rem(self.file_manager.files().len()) ;
-                            selected = res.clamp(0, self.file_manager.files().len()- (1 + overflow_len));
+                            let overflow_len = (usize::MAX - res).strict_rem(self.tab.file_manager.files().len()) ;
+                            selected = res.clamp(0, self.tab.file_manager.files().len()- (1 + overflow_len));
                         }
                         else {
                             selected = res;
@@ -143,60 +323,216 @@ impl App {
                     else {
                         let (res, overflow) = selected.overflowing_sub(1);
                         if overflow {
-                            selected = self.file_manager.files().len() - 1;
+                            selected = self.tab.file_manager.files().len() - 1;
                         }
                         else {
                             selected = res;
                         }
                     }
-                    self.list_state.select(Some(selected));
-                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.list_state.selected()));
+                    self.tab.list_state.select(Some(selected));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.list_state.selected()));
                 },
                 KeyCode::Down | KeyCode::Char('j') => {
-                    if self.file_manager.files().is_empty() {
+                    if self.tab.file_manager.files().is_empty() {
                         return;
                     }
-                    let mut selected = match self.list_state.selected() {
+                    let mut selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => {
-                            self.list_state.select(self.min_selected());
-                            let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.min_selected()));
+                            self.tab.list_state.select(self.tab.min_selected());
+                            let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.min_selected()));
                             return;
                         },
                     };
 
                     if modifiers.contains(KeyModifiers::CONTROL) {
-                        selected = selected + 5;
-                        selected = selected.strict_rem(self.file_manager.files().len());
+                        selected += 5;
+                        selected = selected.strict_rem(self.tab.file_manager.files().len());
                     }
                     else {
-                        selected = selected + 1;
-                        selected = selected.strict_rem(self.file_manager.files().len());
+                        selected += 1;
+                        selected = selected.strict_rem(self.tab.file_manager.files().len());
                     }
-                    self.list_state.select(Some(selected));
-                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.list_state.selected()));
+                    self.tab.list_state.select(Some(selected));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.list_state.selected()));
                 },
                 KeyCode::Enter | KeyCode::Char('l') => {
-                    let selected = match self.list_state.selected() {
+                    let selected = match self.tab.list_state.selected() {
+                        Some(selected) => selected,
+                        None => return,
+                    };
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::Open(selected));
+                },
+                // the folders are expanded inline with Right and collapsed with Left, Left on a row of a subfolder collapses it
+                KeyCode::Right if !modifiers.contains(KeyModifiers::ALT) => {
+                    if let Some(selected) = self.tab.list_state.selected() {
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::Expand(selected));
+                    }
+                },
+                KeyCode::Left if !modifiers.contains(KeyModifiers::ALT) => {
+                    if let Some(selected) = self.tab.list_state.selected() {
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::Collapse(selected));
+                        self.tab.follow_selection();
+                        if self.tab.list_state.selected() != Some(selected) {
+                            let _ = self.tab.file_manager.dispatch(FileManagerAction::ReadContent(self.tab.list_state.selected()));
+                        }
+                    }
+                },
+                KeyCode::Char('J') => {
+                    let selected = match self.tab.list_state.selected() {
                         Some(selected) => selected,
                         None => return,
                     };
-                    let _ = self.file_manager.dispatch(FileManagerAction::Open(selected));
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::JumpToLinkTarget(selected));
+                },
+                KeyCode::Left if modifiers.contains(KeyModifiers::ALT) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::GoBack);
+                },
+                KeyCode::Right if modifiers.contains(KeyModifiers::ALT) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::GoForward);
+                },
+                KeyCode::Char('H') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::GoBack);
+                },
+                KeyCode::Char('L') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::GoForward);
                 },
                 KeyCode::Backspace | KeyCode::Char('h') => {
-                    let _ = self.file_manager.dispatch(FileManagerAction::GoToParent);
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::GoToParent);
                 },
                 KeyCode::F(5) => {
-                    let _ = self.file_manager.dispatch(FileManagerAction::Reload);
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::Reload);
                 },
-                KeyCode::Char('f') => {
-                    if modifiers.contains(KeyModifiers::CONTROL) {
-                        self.filter_mode = !self.filter_mode;
-                        self.filter_buffer.clear();
-                        self.update_filtered_files();
-                        // for convenience, we do not touch to the selection index cause if it's empty it stays empty and if it's different from None then it's still valid cause default filter is empty
+                // the encoding can only be forced on a file
+                KeyCode::Char('e') if self.tab.file_manager.selected_file().is_some_and(|file| file.is_file()) => {
+                    self.popup = Some(Popup::Encoding{selected: 0});
+                },
+                // in dual pane mode Tab goes to the other pane and Shift+Tab to the preview when it's displayed
+                KeyCode::Tab if self.pane.is_some() => {
+                    self.switch_pane();
+                },
+                KeyCode::Tab => {
+                    self.focus = FocusScreen::Preview;
+                },
+                KeyCode::BackTab if self.pane.is_some() && self.show_preview => {
+                    self.focus = FocusScreen::Preview;
+                },
+                KeyCode::Char('D') => {
+                    self.toggle_dual_pane();
+                },
+                KeyCode::Char('S') if self.pane.is_some() => {
+                    self.active_left = !self.active_left;
+                },
+                KeyCode::Char('=') => {
+                    self.sync_panes();
+                },
+                KeyCode::Char('V') => {
+                    self.toggle_miller();
+                },
+                KeyCode::Char('P') if self.pane.is_some() => {
+                    self.show_preview = !self.show_preview;
+                    self.focus = FocusScreen::Files;
+                },
+                KeyCode::Char(letter @ ('C' | 'M')) => {
+                    let mode = if letter == 'C' { TransferMode::Copy } else { TransferMode::Move };
+                    self.popup = Some(Popup::Transfer{mode, destination: self.transfer_destination(), policy: ConflictPolicy::Skip});
+                },
+                // the recursive listing replaces the folder listing, + and - change its depth
+                KeyCode::Char('F') => {
+                    let depth = match self.tab.file_manager.walk_depth() {
+                        Some(_) => None,
+                        None => Some(DEFAULT_WALK_DEPTH),
+                    };
+                    self.tab.list_recursively(depth);
+                },
+                KeyCode::Char(sign @ ('+' | '-')) => {
+                    if let Some(depth) = self.tab.file_manager.walk_depth() {
+                        let depth = if sign == '+' { depth + 1 } else { depth.saturating_sub(1) };
+                        self.tab.list_recursively(Some(depth));
+                    }
+                },
+                KeyCode::Char('.') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleHidden);
+                },
+                KeyCode::Char('f') if !modifiers.contains(KeyModifiers::CONTROL) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleFollow);
+                },
+                // the total size of the folders is measured on demand, or automatically for each listing
+                KeyCode::Char('u') if modifiers.contains(KeyModifiers::ALT) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleCrossFilesystems);
+                },
+                KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleDiskUsage);
+                },
+                KeyCode::Char('u') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::MeasureFolders(self.tab.file_manager.selected_index()));
+                },
+                KeyCode::Char('U') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleAutoMeasure);
+                },
+                KeyCode::Char('I') => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::ShowCacheStats);
+                },
+                KeyCode::Char(' ') => {
+                    if let Some(selected) = self.tab.list_state.selected() {
+                        let _ = self.tab.file_manager.dispatch(FileManagerAction::ToggleMark(selected));
+                    }
+                },
+                KeyCode::Char('x') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    let _ = self.tab.file_manager.dispatch(FileManagerAction::CancelJobs);
+                },
+                // the extraction folder defaults to the archive name without its extensions
+                KeyCode::Char('x') => {
+                    if let Some(file) = self.tab.file_manager.selected_file()
+                        && let Some(kind) = ArchiveKind::detect(std::path::Path::new(file.base_name())) {
+                        let name = kind.stem(file.base_name()).unwrap_or_default().to_string();
+                        self.popup = Some(Popup::Extract{name, policy: ConflictPolicy::Skip});
                     }
                 },
+                // the archive name defaults to the single file compressed, to the current folder name otherwise
+                KeyCode::Char('c') => {
+                    let name = match (self.tab.file_manager.has_marks(), self.tab.file_manager.selected_file()) {
+                        (false, Some(file)) => file.base_name().to_string(),
+                        _ => self.tab.file_manager.path().file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(String::from("archive")),
+                    };
+                    self.popup = Some(Popup::Compress{name, kind: 0, policy: ConflictPolicy::Skip});
+                },
+                KeyCode::Char(prefix @ ('m' | '\'')) => {
+                    self.pending_key = Some(prefix);
+                },
+                KeyCode::Char('b') => {
+                    self.popup = Some(Popup::Bookmarks(self.bookmarks_picker()));
+                },
+                KeyCode::Char('t') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_tab(false);
+                },
+                KeyCode::Char('T') => {
+                    self.open_tab(true);
+                },
+                KeyCode::Char('w') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.close_tab();
+                },
+                KeyCode::Char(']') => {
+                    self.switch_tab((self.active + 1) % self.tabs_count());
+                },
+                KeyCode::Char('[') => {
+                    self.switch_tab(self.active.checked_sub(1).unwrap_or(self.tabs_count() - 1));
+                },
+                KeyCode::Char(digit @ '1'..='9') if modifiers.contains(KeyModifiers::ALT) => {
+                    self.switch_tab(digit as usize - '1' as usize);
+                },
+                KeyCode::Char('g') => {
+                    self.popup = Some(Popup::GoTo(PathPrompt::default()));
+                },
+                KeyCode::Char('z') => {
+                    self.popup = Some(Popup::Jump(self.jump_picker()));
+                },
+                KeyCode::Char('f') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.tab.filter_mode = !self.tab.filter_mode;
+                    self.tab.filter_buffer.clear();
+                    self.tab.update_filtered_files();
+                    // for convenience, we do not touch to the selection index cause if it's empty it stays empty and if it's different from None then it's still valid cause default filter is empty
+                },
                 _ => {}
             }
         }
@@ -1,44 +1,51 @@
 mod input;
 mod ui;
+mod tab;
 
 use crate::file_manager::{FileManager, FileManagerAction};
-use crate::file::FileType;
 use crate::workers::{LightWorkerResponse, LightWorkerError, LightWorkerMessage, FsLightWorker};
-use ratatui::{
-    buffer::Buffer, layout::Rect, text::{Line, Text}, widgets::{Block, List, Padding, Paragraph, StatefulWidget, Widget}
-};
+use crate::workers::{HeavyWorkerResponse, HeavyWorkerError, HeavyWorkerMessage, FsHeavyWorker};
 use crossterm::event::{ Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::io;
-use ratatui::layout::{Layout, Direction, Constraint};
-use ratatui::widgets::ListState;
-use crate::popup::Popup;
+use crate::utility::path::home_dir;
+use crate::popup::{Popup, Picker, PickerItem};
+use tab::Tab;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
-use crate::file::File;
 
 // Min char size width for the name column
 pub static MIN_NAME_WIDTH: usize = 20;
 // Max char size width for the size column
-pub static _MAX_SIZE_WIDTH: usize = 10;
+pub static MAX_SIZE_WIDTH: usize = 10;
 // Max char size width for the type column
 pub static _MAX_TYPE_WIDTH: usize = 13;
 // Max char size width for the modified column
 pub static MODIFIED_TIME_WIDTH: usize = 20;
+// Weight of the frecency of a folder in its jump picker rank, the frecency is taken on a log scale to not hide the fuzzy match
+static FRECENCY_WEIGHT: f64 = 16.0;
 // Min files section width on the UI
 pub static MIN_FILES_SECTION_WIDTH: u16 = 50;
 
 
 pub struct App {
-    file_manager: FileManager,
-    list_state: ListState,
+    tab: Tab,
+    // the other tabs in their order, the active tab sits at the active index between them
+    parked: Vec<Tab>,
+    active: usize,
+    // inactive pane of the dual pane mode, kept apart from the tabs
+    pane: Option<Tab>,
+    // true when the active pane is the left one
+    active_left: bool,
+    // the preview is a third column in dual pane mode, hidden by default
+    show_preview: bool,
+    // ranger-like layout with the parent folder listed left of the files, single pane only
+    miller: bool,
     focus: FocusScreen,
-    filter_mode: bool,
-    filter_buffer: String,
-    filtered_files: Vec<usize>,
     popup: Option<Popup>,
+    // first key of a two keys command, `m` to set a bookmark and `'` to jump to one
+    pending_key: Option<char>,
     max_name_width: usize,
-    light_receiver: mpsc::Receiver<Result<LightWorkerResponse, LightWorkerError>>,
     shutdown: bool,
 }
 
@@ -49,27 +56,26 @@ enum FocusScreen {
 
 
 impl App {
-    pub fn new(file_manager: FileManager, light_receiver: mpsc::Receiver<Result<LightWorkerResponse, LightWorkerError>>) -> Self {
-
-        let mut state = ListState::default();
-        state.select(None);
+    pub fn new(file_manager: FileManager, light_receiver: mpsc::Receiver<Result<LightWorkerResponse, LightWorkerError>>, heavy_receiver: mpsc::Receiver<Result<HeavyWorkerResponse, HeavyWorkerError>>) -> Self {
         Self { 
-            file_manager, 
-            light_receiver,
-            list_state: state, 
+            tab: Tab::new(file_manager, light_receiver, heavy_receiver),
+            parked: Vec::new(),
+            active: 0,
+            pane: None,
+            active_left: true,
+            show_preview: false,
+            miller: false,
             focus: FocusScreen::Files, 
             popup: None, 
+            pending_key: None,
             max_name_width: MIN_NAME_WIDTH,
-            filter_mode: false,
-            filter_buffer: String::new(),
-            filtered_files: Vec::new(),
             shutdown: false,
         }
     }
 
     pub fn spawn_light_worker(&mut self, sender: Option<mpsc::Sender<Result<LightWorkerResponse, LightWorkerError>>>, receiver: Option<mpsc::Receiver<LightWorkerMessage>>) {
         if let (Some(sender), Some(receiver)) = (sender, receiver) {
-            let mut light_worker = FsLightWorker::new(self.file_manager.light_sync_id(), receiver, sender);
+            let mut light_worker = FsLightWorker::new(self.tab.file_manager.light_sync_id(), receiver, sender);
             thread::spawn(move || {
                 let _ = light_worker.run();
             });
@@ -77,14 +83,35 @@ impl App {
         else {
             let (file_manager_sender, light_worker_receiver) = mpsc::channel();
             let (light_worker_sender, app_receiver) = mpsc::channel();
-            self.file_manager.set_light_worker_channel(file_manager_sender);
-            self.light_receiver = app_receiver;
+            self.tab.file_manager.set_light_worker_channel(file_manager_sender);
+            self.tab.light_receiver = app_receiver;
             self.spawn_light_worker(Some(light_worker_sender), Some(light_worker_receiver));
         }
     }
 
+    pub fn spawn_heavy_worker(&mut self, sender: Option<mpsc::Sender<Result<HeavyWorkerResponse, HeavyWorkerError>>>, receiver: Option<mpsc::Receiver<HeavyWorkerMessage>>) {
+        if let (Some(sender), Some(receiver)) = (sender, receiver) {
+            let mut heavy_worker = FsHeavyWorker::new(receiver, sender);
+            thread::spawn(move || {
+                let _ = heavy_worker.run();
+            });
+        }
+        else {
+            let (file_manager_sender, heavy_worker_receiver) = mpsc::channel();
+            let (heavy_worker_sender, app_receiver) = mpsc::channel();
+            self.tab.file_manager.set_heavy_worker_channel(file_manager_sender);
+            self.tab.heavy_receiver = app_receiver;
+            self.spawn_heavy_worker(Some(heavy_worker_sender), Some(heavy_worker_receiver));
+        }
+    }
+
+    /// Starts the watcher of the first tab
+    pub fn spawn_watcher(&mut self) {
+        self.tab.spawn_watcher();
+    }
+
     pub fn run(mut self, terminal: &mut ratatui::DefaultTerminal) -> io::Result<()> {
-        let _ = self.file_manager.dispatch(FileManagerAction::Reload);
+        let _ = self.tab.file_manager.dispatch(FileManagerAction::Reload);
         while !self.shutdown {
 
             // frame rendering
@@ -101,20 +128,20 @@ impl App {
                     break;
                 }
                 let event = crossterm::event::read()?;
-                if let Some(popup) = &mut self.popup {
-                    //popup.dispatch(event);
+                if self.popup.is_some() {
+                    self.handle_popup_input(event);
                 }
                 else {
-                    if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event {
-                        if modifiers.contains(KeyModifiers::CONTROL) && (code == KeyCode::Char('q') || code == KeyCode::Char('c')) {
-                            self.file_manager.shutdown();
-                            self.shutdown = true;
-                            break;
-                        }
+                    if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, modifiers, .. }) = event
+                        && modifiers.contains(KeyModifiers::CONTROL) && (code == KeyCode::Char('q') || code == KeyCode::Char('c')) {
+                        self.tab.file_manager.shutdown();
+                        self.parked.iter().chain(self.pane.iter()).for_each(|tab| tab.file_manager.shutdown());
+                        self.shutdown = true;
+                        break;
                     }
                     match self.focus {
                         FocusScreen::Files => {
-                            if self.filter_mode {
+                            if self.tab.filter_mode {
                                 self.handle_filter_input(event);
                             }
                             else {
@@ -122,71 +149,272 @@ impl App {
                             }
                         }
                         FocusScreen::Preview => {
-                            //self.handle_preview_input(event);
+                            self.handle_preview_input(event);
                         }
                     }
                 }
             }
 
-            // workers response handling
-            while let Ok(response) = self.light_receiver.try_recv() {
-                match response {
-                    Ok(response) => {
-                        match response {
-                            LightWorkerResponse::Loaded(_, _) => {
-                                // load the new files into the file manager to display them in the ui
-                                self.file_manager.consume_response(response);
-                                if self.filter_mode {
-                                    // clear the filter buffer and update the filtered files vector then select the first file in the list and dispatch the read content action for it
-                                    self.filter_buffer.clear();
-                                    self.update_filtered_files();
-                                    let min = self.min_filtered_selected();
-                                    self.list_state.select(min);
-                                    if let Some(min) = min {
-                                        let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(self.filtered_files[min])));
-                                    }
-                                }
-                                else {
-                                    // select the first file in the list and dispatch the read content action for it
-                                    let min = self.min_selected();
-                                    self.list_state.select(min);
-                                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(min));
-                                }
-                            },
-                            LightWorkerResponse::Read(..) => {
-                                // update the selected file preview buffer in the file manager to display it in the ui
-                                self.file_manager.consume_response(response);
-                            },
-                        }
-                    },
-                    Err(_error) => {
-                        self.file_manager.increment_light_sync_id();
-                    },
-                }
+            // workers response handling, each tab consumes the responses of its own workers
+            // the other pane is reloaded when a job of the active one ends as it's the default destination of the copies and moves
+            if self.tab.handle_responses() && let Some(pane) = &mut self.pane {
+                let _ = pane.file_manager.dispatch(FileManagerAction::Reload);
+            }
+            if let Some(pane) = &mut self.pane {
+                pane.handle_responses();
+            }
+            for tab in self.parked.iter_mut() {
+                tab.handle_responses();
             }
         }
         Ok(())
     }
 
-    /// Returns the minimum index that can be selected in the file list, if the list is empty, returns None
-    fn min_selected(&self) -> Option<usize> {
-        match self.file_manager.files().len() {
-            0 => None,
-            _ => Some(0),
+    /// Builds the picker over the bookmarks, the index of an item is the index of its bookmark
+    fn bookmarks_picker(&self) -> Picker {
+        let items = self.tab.file_manager.bookmarks().entries().iter().map(|bookmark| PickerItem {
+            label: format!("[{}] {}", bookmark.key.unwrap_or(' '), bookmark.name),
+            path: bookmark.path.clone(),
+            missing: !bookmark.exists(),
+            rank: 0,
+        }).collect();
+        Picker::new(" Bookmarks - Del to remove, Ctrl+g to import GTK bookmarks ", items)
+    }
+
+    /// Builds the picker over the visited folders ranked by frecency, the current folder is left out
+    fn jump_picker(&self) -> Picker {
+        let items = self.tab.file_manager.frecency().ranked().into_iter()
+            .filter(|(visit, _)| visit.path != *self.tab.file_manager.path())
+            .map(|(visit, frecency)| PickerItem {
+                label: visit.path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(String::from("/")),
+                path: visit.path.clone(),
+                missing: false,
+                rank: (frecency.ln_1p() * FRECENCY_WEIGHT) as i64,
+            }).collect();
+        Picker::new(" Jump ", items)
+    }
+
+    /// Creates a tab with its own workers on the home folder, or on a copy of the active tab
+    fn create_tab(&self, duplicate: bool) -> Tab {
+        let (file_manager_sender, light_worker_receiver) = mpsc::channel();
+        let (light_worker_sender, app_receiver) = mpsc::channel();
+        let (file_manager_heavy_sender, heavy_worker_receiver) = mpsc::channel();
+        let (heavy_worker_sender, app_heavy_receiver) = mpsc::channel();
+        let mut file_manager = match (duplicate, home_dir()) {
+            (false, Some(home)) => self.tab.file_manager.new_tab(&home, file_manager_sender, file_manager_heavy_sender),
+            _ => self.tab.file_manager.duplicate(file_manager_sender, file_manager_heavy_sender),
+        };
+        let mut light_worker = FsLightWorker::new(file_manager.light_sync_id(), light_worker_receiver, light_worker_sender);
+        thread::spawn(move || {
+            let _ = light_worker.run();
+        });
+        let mut heavy_worker = FsHeavyWorker::new(heavy_worker_receiver, heavy_worker_sender);
+        thread::spawn(move || {
+            let _ = heavy_worker.run();
+        });
+        let _ = file_manager.dispatch(FileManagerAction::Reload);
+        let mut tab = Tab::new(file_manager, app_receiver, app_heavy_receiver);
+        tab.spawn_watcher();
+        tab
+    }
+
+    /// Opens a tab after the active one and switches to it
+    fn open_tab(&mut self, duplicate: bool) {
+        if !self.tabs_available() {
+            return;
+        }
+        let tab = self.create_tab(duplicate);
+        self.insert_tab(tab);
+    }
+
+    /// Parks the active tab and makes the tab the active one, right after it
+    fn insert_tab(&mut self, tab: Tab) {
+        let previous = std::mem::replace(&mut self.tab, tab);
+        self.parked.insert(self.active, previous);
+        self.active += 1;
+    }
+
+    /// Closes the active tab and switches to the next one, the last tab and the tabs with running jobs are not closed
+    fn close_tab(&mut self) {
+        if !self.tabs_available() {
+            return;
+        }
+        if !self.tab.file_manager.jobs().is_empty() {
+            self.tab.file_manager.set_status(String::from("Cancel the jobs of the tab before closing it"));
+            return;
+        }
+        let next = match self.parked.len() {
+            0 => return,
+            length if self.active < length => self.parked.remove(self.active),
+            _ => {
+                self.active -= 1;
+                self.parked.remove(self.active)
+            },
+        };
+        std::mem::replace(&mut self.tab, next).file_manager.shutdown();
+    }
+
+    /// Switches to the tab at the index in the tab bar
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.active || index > self.parked.len() || !self.tabs_available() {
+            return;
+        }
+        // the tabs after the active one are shifted by one in the parked tabs
+        let next = self.parked.remove(if index > self.active { index - 1 } else { index });
+        let previous = std::mem::replace(&mut self.tab, next);
+        self.parked.insert(if self.active < index { self.active } else { self.active - 1 }, previous);
+        self.active = index;
+        let _ = self.tab.file_manager.dispatch(FileManagerAction::ShowParent(self.miller));
+    }
+
+    /// Returns true if the tabs can be opened, closed or switched, they are not in dual pane mode where the active tab may be the right pane
+    fn tabs_available(&mut self) -> bool {
+        if self.pane.is_some() {
+            self.tab.file_manager.set_status(String::from("Tabs are not available in dual pane mode"));
+        }
+        self.pane.is_none()
+    }
+
+    fn tabs_count(&self) -> usize {
+        self.parked.len() + 1
+    }
+
+    /// Returns the titles of all the tabs in their order, the active tab is the left pane in dual pane mode
+    fn tab_titles(&self) -> Vec<String> {
+        let mut titles = self.parked.iter().map(Tab::title).collect::<Vec<String>>();
+        let active = match &self.pane {
+            Some(pane) if !self.active_left => pane.title(),
+            _ => self.tab.title(),
+        };
+        titles.insert(self.active, active);
+        titles
+    }
+
+    /// Turns the dual pane mode on with the other pane on the same folder, or off if the other pane has no running jobs
+    fn toggle_dual_pane(&mut self) {
+        match &self.pane {
+            None => {
+                // the miller columns don't fit beside two panes
+                self.miller = false;
+                let _ = self.tab.file_manager.dispatch(FileManagerAction::ShowParent(false));
+                self.pane = Some(self.create_tab(true));
+                self.active_left = true;
+                self.focus = FocusScreen::Files;
+            },
+            Some(pane) if !pane.file_manager.jobs().is_empty() => {
+                self.tab.file_manager.set_status(String::from("Cancel the jobs of the other pane before closing it"));
+            },
+            Some(_) => {
+                if let Some(pane) = self.pane.take() {
+                    pane.file_manager.shutdown();
+                }
+            },
+        }
+    }
+
+    /// Turns the miller columns on or off, they are not available in dual pane mode
+    fn toggle_miller(&mut self) {
+        if self.pane.is_some() {
+            self.tab.file_manager.set_status(String::from("Miller columns are not available in dual pane mode"));
+            return;
         }
+        self.miller = !self.miller;
+        let _ = self.tab.file_manager.dispatch(FileManagerAction::ShowParent(self.miller));
     }
 
-    /// Returns the minimum index that can be selected in the filtered file list, if the list is empty, returns None
-    fn min_filtered_selected(&self) -> Option<usize> {
-        match self.filtered_files.len() {
-            0 => None,
-            _ => Some(0),
+    /// Makes the other pane the active one, it takes the place of the active tab
+    fn switch_pane(&mut self) {
+        if let Some(pane) = &mut self.pane {
+            std::mem::swap(&mut self.tab, pane);
+            self.active_left = !self.active_left;
         }
     }
 
-    /// update the filtered files vector with the current filter buffer
-    fn update_filtered_files(&mut self) {
-        let new_filtered_files : Vec<usize> = self.file_manager.files().iter().enumerate().filter(|(_ , file)| file.name().contains(&self.filter_buffer)).map(|(index, _)| index).collect();
-        self.filtered_files = new_filtered_files;
+    /// Loads the folder of the active pane in the other pane
+    fn sync_panes(&mut self) {
+        let path = self.tab.file_manager.path().clone();
+        if let Some(pane) = &mut self.pane {
+            let _ = pane.file_manager.dispatch(FileManagerAction::OpenPath(path));
+        }
     }
-}
\ No newline at end of file
+
+    /// Returns the default destination of a copy or a move, the folder of the other pane in dual pane mode
+    fn transfer_destination(&self) -> String {
+        let folder = match &self.pane {
+            Some(pane) => pane.file_manager.path(),
+            None => self.tab.file_manager.path(),
+        };
+        folder.to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::mpsc;
+    use crate::file_manager::FileManager;
+    use super::{App, Tab};
+
+    /// Tab without workers on the folder, its title is the folder name
+    fn tab(name: &str) -> Tab {
+        let file_manager = FileManager::new(&Path::new("/").join(name), 0, mpsc::channel().0, mpsc::channel().0);
+        Tab::new(file_manager, mpsc::channel().1, mpsc::channel().1)
+    }
+
+    /// Returns the titles of the tabs, the active one and its index
+    fn tabs(app: &App) -> (Vec<String>, String, usize) {
+        (app.tab_titles(), app.tab.title(), app.active)
+    }
+
+    fn titles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tabs() {
+        let first = tab("t0");
+        let mut app = App::new(first.file_manager, first.light_receiver, first.heavy_receiver);
+        // the only tab is not closed
+        app.close_tab();
+        assert_eq!(tabs(&app), (titles(&["t0"]), "t0".to_string(), 0));
+        app.insert_tab(tab("t1"));
+        app.insert_tab(tab("t2"));
+        assert_eq!(tabs(&app), (titles(&["t0", "t1", "t2"]), "t2".to_string(), 2));
+        // a tab opens right after the active one
+        app.switch_tab(0);
+        app.insert_tab(tab("t3"));
+        assert_eq!(tabs(&app), (titles(&["t0", "t3", "t1", "t2"]), "t3".to_string(), 1));
+        app.switch_tab(3);
+        assert_eq!(tabs(&app), (titles(&["t0", "t3", "t1", "t2"]), "t2".to_string(), 3));
+        app.switch_tab(4);
+        assert_eq!(app.active, 3);
+        // closing the rightmost tab activates the one before it, closing another one activates the next one
+        app.close_tab();
+        assert_eq!(tabs(&app), (titles(&["t0", "t3", "t1"]), "t1".to_string(), 2));
+        app.switch_tab(1);
+        app.close_tab();
+        assert_eq!(tabs(&app), (titles(&["t0", "t1"]), "t1".to_string(), 1));
+        app.switch_tab(0);
+        app.close_tab();
+        assert_eq!(tabs(&app), (titles(&["t1"]), "t1".to_string(), 0));
+        // each tab keeps its own file manager through the moves
+        app.insert_tab(tab("t4"));
+        app.switch_tab(0);
+        assert_eq!(app.tab.file_manager.path(), Path::new("/t1"));
+        assert_eq!(app.parked[0].file_manager.path(), Path::new("/t4"));
+    }
+
+    #[test]
+    fn test_tabs_in_dual_pane() {
+        let first = tab("t0");
+        let mut app = App::new(first.file_manager, first.light_receiver, first.heavy_receiver);
+        app.insert_tab(tab("t1"));
+        app.pane = Some(tab("p"));
+        app.switch_tab(0);
+        app.close_tab();
+        assert_eq!(tabs(&app), (titles(&["t0", "t1"]), "t1".to_string(), 1));
+        // the right pane takes the place of the active tab in the tab bar
+        app.active_left = false;
+        assert_eq!(app.tab_titles(), titles(&["t0", "p"]));
+    }
+}
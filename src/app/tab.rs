@@ -0,0 +1,213 @@
+use crate::file_manager::{FileManager, FileManagerAction};
+use crate::workers::{LightWorkerResponse, LightWorkerError, Listing};
+use crate::workers::{HeavyWorkerResponse, HeavyWorkerError};
+use crate::workers::{FsWatcher, WatcherResponse};
+use ratatui::widgets::ListState;
+use std::sync::mpsc;
+use std::thread;
+use std::path::Path;
+use std::collections::HashSet;
+
+/// Folder opened in a tab, each tab has its own file manager and workers so their responses are routed to the tab that requested them
+pub struct Tab {
+    pub file_manager: FileManager,
+    pub list_state: ListState,
+    pub filter_mode: bool,
+    pub filter_buffer: String,
+    pub filtered_files: Vec<usize>,
+    pub light_receiver: mpsc::Receiver<Result<LightWorkerResponse, LightWorkerError>>,
+    pub heavy_receiver: mpsc::Receiver<Result<HeavyWorkerResponse, HeavyWorkerError>>,
+    watcher_receiver: Option<mpsc::Receiver<WatcherResponse>>,
+}
+
+impl Tab {
+    pub fn new(file_manager: FileManager, light_receiver: mpsc::Receiver<Result<LightWorkerResponse, LightWorkerError>>, heavy_receiver: mpsc::Receiver<Result<HeavyWorkerResponse, HeavyWorkerError>>) -> Self {
+        Self {
+            file_manager,
+            list_state: ListState::default(),
+            filter_mode: false,
+            filter_buffer: String::new(),
+            filtered_files: Vec::new(),
+            light_receiver,
+            heavy_receiver,
+            watcher_receiver: None,
+        }
+    }
+
+    /// Starts the watcher of the tab folder, the tab is not watched if inotify can't be initialized
+    pub fn spawn_watcher(&mut self) {
+        let (file_manager_sender, watcher_receiver) = mpsc::channel();
+        let (watcher_sender, tab_receiver) = mpsc::channel();
+        if let Ok(mut watcher) = FsWatcher::new(watcher_receiver, watcher_sender) {
+            thread::spawn(move || watcher.run());
+            self.file_manager.set_watcher_channel(file_manager_sender);
+            self.watcher_receiver = Some(tab_receiver);
+        }
+    }
+
+    /// Name displayed in the tab bar, the name of the current folder
+    pub fn title(&self) -> String {
+        match self.file_manager.path().file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.file_manager.path().to_string_lossy().to_string(),
+        }
+    }
+
+    /// Consumes the pending responses of the tab workers, returns true if a job ended
+    pub fn handle_responses(&mut self) -> bool {
+        let mut job_ended = false;
+        while let Ok(response) = self.light_receiver.try_recv() {
+            match response {
+                Ok(response) => {
+                    match response {
+                        LightWorkerResponse::Loading{first: false, ..} | LightWorkerResponse::Loaded(Listing{streamed: true, ..}) => {
+                            // a big folder grows batch by batch, the file asked for by the navigation is selected once its batch comes in
+                            self.file_manager.consume_response(response);
+                            self.follow_selection();
+                            if let Some(index) = self.file_manager.take_pending_selection() {
+                                let row = match self.filter_mode {
+                                    true => self.filtered_files.iter().position(|filtered| *filtered == index),
+                                    false => Some(index),
+                                };
+                                self.list_state.select(row);
+                                let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(Some(index)));
+                            }
+                            self.show_added_files();
+                        },
+                        LightWorkerResponse::Loading{..} | LightWorkerResponse::Loaded(..) => {
+                            // load the new files into the file manager to display them in the ui
+                            self.file_manager.consume_response(response);
+                            // a refresh after a change on the disk keeps the filter and the preview of the file still selected
+                            if self.file_manager.was_refreshed() && self.file_manager.selected_index().is_some() {
+                                self.follow_selection();
+                                if self.file_manager.has_pending_reread() {
+                                    let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(self.file_manager.selected_index()));
+                                }
+                                continue;
+                            }
+                            if self.filter_mode {
+                                // clear the filter buffer and update the filtered files vector then select the first file in the list and dispatch the read content action for it
+                                self.filter_buffer.clear();
+                                self.update_filtered_files();
+                            }
+                            // select the file chosen by the file manager and dispatch the read content action for it
+                            // (with an empty filter the filtered files indexes are the same as the files ones)
+                            let selected = self.file_manager.take_selection_after_load();
+                            self.list_state.select(selected);
+                            let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(selected));
+                        },
+                        LightWorkerResponse::Expanded(..) => {
+                            // the rows of the subfolder are inserted under it, the selection follows its file
+                            self.file_manager.consume_response(response);
+                            self.follow_selection();
+                        },
+                        LightWorkerResponse::Read(..) => {
+                            // update the selected file preview buffer in the file manager to display it in the ui
+                            self.file_manager.consume_response(response);
+                        },
+                    }
+                },
+                Err(LightWorkerError::LoadFailed(error)) => {
+                    self.file_manager.consume_load_error(error);
+                },
+                Err(_error) => {
+                    self.file_manager.increment_light_sync_id();
+                },
+            }
+        }
+        while let Some(Ok(response)) = self.watcher_receiver.as_ref().map(mpsc::Receiver::try_recv) {
+            self.file_manager.consume_watcher_response(response);
+        }
+        while let Ok(response) = self.heavy_receiver.try_recv() {
+            match response {
+                Ok(response) => {
+                    job_ended |= matches!(response, HeavyWorkerResponse::JobFinished(..));
+                    let walked = matches!(response, HeavyWorkerResponse::Walked{..});
+                    self.file_manager.consume_heavy_response(response);
+                    if walked {
+                        self.show_added_files();
+                    }
+                },
+                Err(HeavyWorkerError::ListFailed(error, path)) => self.file_manager.consume_heavy_error(&path, error.to_string()),
+                Err(HeavyWorkerError::IndexFailed(error, path)) => self.file_manager.consume_index_error(&path, error.to_string()),
+                Err(HeavyWorkerError::WalkFailed(id, error)) => self.file_manager.consume_walk_error(id, error.to_string()),
+                Err(HeavyWorkerError::MeasureFailed(_, path, error)) => self.file_manager.consume_measure_error(path, error.to_string()),
+                Err(HeavyWorkerError::JobFailed(id, error)) => {
+                    job_ended = true;
+                    self.file_manager.consume_job_error(id, error.to_string());
+                },
+                Err(_error) => {},
+            }
+        }
+        job_ended
+    }
+
+    /// Starts a recursive listing of the current folder, or goes back to the folder listing without depth
+    pub fn list_recursively(&mut self, depth: Option<usize>) {
+        let _ = self.file_manager.dispatch(FileManagerAction::ListRecursively(depth));
+        // the files of the recursive listing are selected as they arrive
+        if self.file_manager.walk_depth().is_some() {
+            self.list_state.select(None);
+            self.filtered_files.clear();
+        }
+    }
+
+    /// Shows the new files of the recursive listing or of a big folder, the first one is selected and previewed once it's found
+    fn show_added_files(&mut self) {
+        if self.filter_mode {
+            self.update_filtered_files();
+        }
+        if self.list_state.selected().is_some() {
+            return;
+        }
+        let (row, index) = match self.filter_mode {
+            true => (self.min_filtered_selected(), self.filtered_files.first().copied()),
+            false => (self.min_selected(), self.min_selected()),
+        };
+        if row.is_some() {
+            self.list_state.select(row);
+            let _ = self.file_manager.dispatch(FileManagerAction::ReadContent(index));
+        }
+    }
+
+    /// Returns the minimum index that can be selected in the file list, if the list is empty, returns None
+    pub fn min_selected(&self) -> Option<usize> {
+        match self.file_manager.files().len() {
+            0 => None,
+            _ => Some(0),
+        }
+    }
+
+    /// Returns the minimum index that can be selected in the filtered file list, if the list is empty, returns None
+    pub fn min_filtered_selected(&self) -> Option<usize> {
+        match self.filtered_files.len() {
+            0 => None,
+            _ => Some(0),
+        }
+    }
+
+    /// update the filtered files vector with the current filter buffer
+    /// the rows of the expanded subfolders are matched too, the folders containing a match are kept to show where it is
+    pub fn update_filtered_files(&mut self) {
+        let files = self.file_manager.files();
+        let kept = files.iter()
+            .filter(|file| file.base_name().contains(&self.filter_buffer))
+            .flat_map(|file| Path::new(file.name()).ancestors())
+            .collect::<HashSet<&Path>>();
+        let new_filtered_files : Vec<usize> = files.iter().enumerate().filter(|(_ , file)| kept.contains(Path::new(file.name()))).map(|(index, _)| index).collect();
+        self.filtered_files = new_filtered_files;
+    }
+
+    /// Selects the row of the file selected in the file manager after rows were added to or removed from the tree
+    pub fn follow_selection(&mut self) {
+        if self.filter_mode {
+            self.update_filtered_files();
+        }
+        let selected = self.file_manager.selected_index();
+        let row = match self.filter_mode {
+            true => selected.and_then(|selected| self.filtered_files.iter().position(|index| *index == selected)),
+            false => selected,
+        };
+        self.list_state.select(row);
+    }
+}
@@ -1,53 +1,171 @@
-use crate::app::App;
+use crate::app::{App, FocusScreen};
 use ratatui::{
-    buffer::Buffer, layout::Rect, text::{Line, Text}, widgets::{Block, List, Padding, Paragraph, StatefulWidget, Widget}
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}, widgets::{Block, List, Padding, Paragraph, StatefulWidget, Widget}
 };
 use ratatui::layout::{Layout, Direction, Constraint};
-use ratatui::style::{Style, Color};
+use ratatui::style::{Style, Color, Modifier};
+use crate::app::tab::Tab;
+use crate::file::FileType;
+use ratatui::widgets::ListState;
+
+// Symbol drawn before the selected file
+static LIST_SYMBOL: &str = "->";
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
 
-        // we shrink preview section to let space for files section if the terminal is too small
-        let area_width = area.width;
-        let files_section_width_constrain = if area_width / 2 > crate::app::MIN_FILES_SECTION_WIDTH { Constraint::Percentage(50) } else { Constraint::Length(crate::app::MIN_FILES_SECTION_WIDTH) };
-        // split the screen into 2 parts horizontally left (Files) and right (File preview if available on selected file)
-        let main_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                files_section_width_constrain,
-                Constraint::Percentage(100),
-            ])
-            .split(area);
-
-        let list_symbol = "->";
-        // dynamically calculate the max name width based on the screen width and the modified time width
-        self.max_name_width = std::cmp::max(crate::app::MIN_NAME_WIDTH, (main_layout[0].width as usize).saturating_sub(crate::app::MODIFIED_TIME_WIDTH+list_symbol.len()));
+        // in dual pane mode the files section holds both panes and the preview is an optional third column
+        let mut parent_area = None;
+        let (files_area, preview_area) = match (&self.pane, self.show_preview) {
+            (None, _) if self.miller => {
+                // the miller columns: parent folder, files and preview
+                let main_layout = Layout::horizontal([Constraint::Ratio(1, 5), Constraint::Ratio(2, 5), Constraint::Ratio(2, 5)]).split(area);
+                parent_area = Some(main_layout[0]);
+                (main_layout[1], Some(main_layout[2]))
+            },
+            (None, _) => {
+                // we shrink preview section to let space for files section if the terminal is too small
+                let files_section_width_constrain = if area.width / 2 > crate::app::MIN_FILES_SECTION_WIDTH { Constraint::Percentage(50) } else { Constraint::Length(crate::app::MIN_FILES_SECTION_WIDTH) };
+                // split the screen into 2 parts horizontally left (Files) and right (File preview if available on selected file)
+                let main_layout = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![
+                        files_section_width_constrain,
+                        Constraint::Percentage(100),
+                    ])
+                    .split(area);
+                (main_layout[0], Some(main_layout[1]))
+            },
+            (Some(_), true) => {
+                let main_layout = Layout::horizontal([Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)]).split(area);
+                (main_layout[0], Some(main_layout[1]))
+            },
+            (Some(_), false) => (area, None),
+        };
 
-        // split the left part into 2 parts vertically top (Path) and bottom (File list)
+        // split the files section vertically into the tab bar, the panes and the status bar
         let files_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
-                Constraint::Length(3),
+                Constraint::Length(1),
                 Constraint::Percentage(100),
                 Constraint::Length(1),
             ])
-            .split(main_layout[0]);
+            .split(files_area);
+        let panes = if self.pane.is_some() { 2 } else { 1 };
+        let columns = Layout::horizontal(vec![Constraint::Ratio(1, panes); panes as usize]).split(files_layout[1]);
 
-        // render the file list
-        let files_items = match self.filter_mode {
-            true => self.filtered_files.iter().map(|index| self.file_manager.files()[*index].to_line(self.max_name_width-list_symbol.len(), crate::app::MODIFIED_TIME_WIDTH)).collect::<Vec<Line>>(),
-            false => self.file_manager.files().iter().map(|file| file.to_line(self.max_name_width-list_symbol.len(), crate::app::MODIFIED_TIME_WIDTH)).collect::<Vec<Line>>(),
-        };
-        let list = List::new(files_items).block(Block::default()).highlight_symbol("->").repeat_highlight_symbol(true);
-        StatefulWidget::render(list, files_layout[1], buf, &mut self.list_state);
+        // dynamically calculate the max name width based on the pane width and the size and modified time widths
+        self.max_name_width = std::cmp::max(crate::app::MIN_NAME_WIDTH, (columns[0].width as usize).saturating_sub(crate::app::MAX_SIZE_WIDTH+crate::app::MODIFIED_TIME_WIDTH+LIST_SYMBOL.len()));
+
+        // render the panes, the active one is drawn on the side it was left on
+        let (active_column, other_column) = if self.active_left { (0, columns.len() - 1) } else { (columns.len() - 1, 0) };
+        render_pane(&mut self.tab, columns[active_column], true, self.max_name_width, buf);
+        if let Some(pane) = &mut self.pane {
+            render_pane(pane, columns[other_column], false, self.max_name_width, buf);
+        }
 
-        // render the path
-        let _path_display = Paragraph::new(Text::from(self.file_manager.path().to_string_lossy().to_string())).block(Block::default().padding(Padding::new(1, 0, 1, 0))).left_aligned().render(files_layout[0], buf);
+        // render the parent folder aligned with the file list, the current folder is highlighted
+        if let Some(area) = parent_area {
+            let area = Rect { y: area.y + 3, height: area.height.saturating_sub(4), ..area };
+            render_parent(&self.tab, area, buf);
+        }
 
-        let _preview_display = Paragraph::new(Text::from(self.file_manager.selected_file_preview_buffer())).block(Block::default().title(Line::from(" Preview ").centered())).render(main_layout[1], buf);
+        // render the tab bar, the active tab is highlighted
+        let tabs = self.tab_titles().into_iter().enumerate().map(|(index, title)| {
+            let style = if index == self.active { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default().fg(Color::DarkGray) };
+            Span::styled(format!(" {} {} ", index + 1, title), style)
+        }).collect::<Vec<Span>>();
+        Line::from(tabs).render(files_layout[0], buf);
 
-        let _filter_mode_display = Paragraph::new(Text::from(if self.filter_mode { format!("Applied filter: \"{}\"", self.filter_buffer) } else { "Filter mode OFF".to_string() }).style(Style::default().black())).style(Style::default().bg(Color::White)).left_aligned().render(files_layout[2], buf);
+        // render the preview of the selected file inside its block
+        // the title is highlighted when the preview has the focus
+        let title_style = match self.focus {
+            FocusScreen::Preview => Style::default().add_modifier(Modifier::REVERSED),
+            FocusScreen::Files => Style::default(),
+        };
+        if let Some(area) = preview_area {
+            let title = match self.tab.file_manager.follows() {
+                true => format!("{}[follow] ", self.tab.file_manager.selected_file_preview().title()),
+                false => self.tab.file_manager.selected_file_preview().title(),
+            };
+            let preview_block = Block::default().title(Line::styled(title, title_style).centered());
+            let preview_area = preview_block.inner(area);
+            preview_block.render(area, buf);
+            self.tab.file_manager.set_preview_area(preview_area.width, preview_area.height);
+            self.tab.file_manager.selected_file_preview_mut().render(preview_area, buf);
+        }
+
+        // the status bar shows the filter when it's on, then the running jobs, then the result of the last job
+        let status = if self.tab.filter_mode {
+            format!("Applied filter: \"{}\"", self.tab.filter_buffer)
+        }
+        else if let Some(job) = self.tab.file_manager.jobs().first() {
+            let others = match self.tab.file_manager.jobs().len() - 1 {
+                0 => String::new(),
+                others => format!(" (+{} jobs)", others),
+            };
+            format!("{} {}%{} - Ctrl+x to cancel", job.description(), job.percent(), others)
+        }
+        else if let Some(status) = self.tab.file_manager.status() {
+            status.clone()
+        }
+        else {
+            "Filter mode OFF".to_string()
+        };
+        Paragraph::new(Text::from(status).style(Style::default().black())).style(Style::default().bg(Color::White)).left_aligned().render(files_layout[2], buf);
     
     }
-}
\ No newline at end of file
+}
+
+/// Renders the path and the file list of a pane, the path of the inactive pane is dimmed
+fn render_pane(tab: &mut Tab, area: Rect, active: bool, max_name_width: usize, buf: &mut Buffer) {
+    let layout = Layout::vertical([Constraint::Length(2), Constraint::Percentage(100)]).split(area);
+
+    // render the file list
+    // the marked files are highlighted
+    let mark_style = |file: &crate::file::File| match tab.file_manager.is_marked(file.name()) {
+        true => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        false => Style::default(),
+    };
+    let row = |index: usize| {
+        let file = &tab.file_manager.files()[index];
+        file.to_line(tab.file_manager.label(index), tab.file_manager.size_label(index), max_name_width-LIST_SYMBOL.len(), crate::app::MAX_SIZE_WIDTH, crate::app::MODIFIED_TIME_WIDTH).style(mark_style(file))
+    };
+    // only the visible rows are built, the offset is moved to keep the selection in view as the list does
+    let count = match tab.filter_mode {
+        true => tab.filtered_files.len(),
+        false => tab.file_manager.files().len(),
+    };
+    let height = (layout[1].height as usize).max(1);
+    let selected = tab.list_state.selected().map(|selected| selected.min(count.saturating_sub(1)));
+    let mut offset = tab.list_state.offset().min(count.saturating_sub(height));
+    if let Some(selected) = selected {
+        offset = offset.clamp((selected + 1).saturating_sub(height), selected);
+    }
+    let visible = offset..(offset + height).min(count);
+    let files_items = match tab.filter_mode {
+        true => tab.filtered_files[visible].iter().map(|index| row(*index)).collect::<Vec<Line>>(),
+        false => visible.map(row).collect::<Vec<Line>>(),
+    };
+    *tab.list_state.offset_mut() = offset;
+    let list = List::new(files_items).block(Block::default()).highlight_symbol(LIST_SYMBOL).repeat_highlight_symbol(true);
+    StatefulWidget::render(list, layout[1], buf, &mut ListState::default().with_selected(selected.map(|selected| selected - offset)));
+
+    // render the path
+    let path_style = if active { Style::default() } else { Style::default().fg(Color::DarkGray) };
+    Paragraph::new(Text::from(tab.file_manager.path().to_string_lossy().to_string()).style(path_style)).block(Block::default().padding(Padding::new(1, 0, 0, 0))).left_aligned().render(layout[0], buf);
+}
+
+/// Renders the listing of the parent folder for the miller columns, nothing is drawn until it's loaded
+fn render_parent(tab: &Tab, area: Rect, buf: &mut Buffer) {
+    let Some((files, selected)) = tab.file_manager.parent_listing() else {
+        return;
+    };
+    let items = files.iter().map(|file| match file.file_type() {
+        FileType::Folder => Line::styled(format!(" {}/", file.name()), Style::default().fg(Color::Blue)),
+        _ => Line::from(format!(" {}", file.name())),
+    }).collect::<Vec<Line>>();
+    let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    StatefulWidget::render(list, area, buf, &mut ListState::default().with_selected(selected));
+}
@@ -0,0 +1,791 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::os::unix::fs::{PermissionsExt, symlink};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use xz2::read::XzDecoder;
+use thiserror::Error;
+use crate::transfer;
+
+// Size of the buffer used to copy the content of an entry
+static COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Archive formats that can be listed and extracted
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+/// Archive formats an archive can be created in
+pub static CREATABLE_KINDS: [ArchiveKind; 3] = [ArchiveKind::Zip, ArchiveKind::TarGz, ArchiveKind::TarZst];
+
+impl ArchiveKind {
+    /// Detects the archive format from the file name, tarballs are recognized by their double extension
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(ArchiveKind::Zip)
+        }
+        else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        }
+        else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        }
+        else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(ArchiveKind::TarXz)
+        }
+        else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(ArchiveKind::TarZst)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => "ZIP",
+            ArchiveKind::Tar => "TAR",
+            ArchiveKind::TarGz => "TAR.GZ",
+            ArchiveKind::TarXz => "TAR.XZ",
+            ArchiveKind::TarZst => "TAR.ZST",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => ".zip",
+            ArchiveKind::Tar => ".tar",
+            ArchiveKind::TarGz => ".tar.gz",
+            ArchiveKind::TarXz => ".tar.xz",
+            ArchiveKind::TarZst => ".tar.zst",
+        }
+    }
+
+    /// Returns the file name without the extension of the format, compared without case on the original name
+    pub fn stem<'a>(&self, name: &'a str) -> Option<&'a str> {
+        let extension = self.extension();
+        let split = name.len().checked_sub(extension.len())?;
+        match name.is_char_boundary(split) && name[split..].eq_ignore_ascii_case(extension) {
+            true => Some(&name[..split]),
+            false => None,
+        }
+    }
+}
+
+/// Decision taken when a file to write already exists
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl ConflictPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "Skip",
+            ConflictPolicy::Overwrite => "Overwrite",
+            ConflictPolicy::Rename => "Rename",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ConflictPolicy::Skip => ConflictPolicy::Overwrite,
+            ConflictPolicy::Overwrite => ConflictPolicy::Rename,
+            ConflictPolicy::Rename => ConflictPolicy::Skip,
+        }
+    }
+
+    /// Returns the path to write to, None if the existing file must be kept
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        if fs::symlink_metadata(path).is_err() {
+            return Some(path.to_path_buf());
+        }
+        match self {
+            ConflictPolicy::Skip => None,
+            ConflictPolicy::Overwrite => Some(path.to_path_buf()),
+            ConflictPolicy::Rename => Some(available_name(path)),
+        }
+    }
+}
+
+/// Returns the first free path made of the name followed by a counter (e.g. `x (1).tar.gz`), the extensions are kept after the counter
+fn available_name(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    // the extensions start at the first dot that doesn't start the name (e.g. `.bashrc` has no extension)
+    let (stem, extensions) = match name.char_indices().skip(1).find(|(_, character)| *character == '.') {
+        Some((index, _)) => name.split_at(index),
+        None => (name.as_str(), ""),
+    };
+    (1..).map(|counter| path.with_file_name(format!("{} ({}){}", stem, counter, extensions)))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap_or(path.to_path_buf())
+}
+
+/// Receives the progress of a long archive operation
+pub trait Progress {
+    /// Called once the total number of bytes to process is known
+    fn set_total(&mut self, total: u64);
+    /// Called with the number of bytes processed since the last call, an error aborts the operation
+    fn advance(&mut self, bytes: u64) -> io::Result<()>;
+}
+
+/// Progress ignoring the updates, for the tests
+#[cfg(test)]
+pub struct NoProgress;
+
+#[cfg(test)]
+impl Progress for NoProgress {
+    fn set_total(&mut self, _total: u64) {}
+    fn advance(&mut self, _bytes: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Error returned by a progress to abort the operation when the user cancels it
+#[derive(Error, Debug)]
+#[error("Cancelled")]
+pub struct Cancelled;
+
+/// Result of an extraction
+#[derive(Debug, Default)]
+pub struct ExtractSummary {
+    pub extracted: usize,
+    pub skipped: usize,
+}
+
+/// Entry of an archive as stored in its index, the path is relative to the archive root
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub mode: Option<u32>,
+    pub modified: Option<SystemTime>,
+}
+
+// Suffix marking an archive as a folder in a virtual path (e.g. `/tmp/x.tar.gz!/src/lib`)
+static VIRTUAL_ROOT_SUFFIX: char = '!';
+
+/// Returns the virtual path of the root of the archive, the path browsed when the archive is opened as a folder
+pub fn virtual_root(archive: &Path) -> PathBuf {
+    let mut root = archive.as_os_str().to_os_string();
+    root.push(VIRTUAL_ROOT_SUFFIX.to_string());
+    PathBuf::from(root)
+}
+
+/// Splits a virtual path into the archive file and the path inside the archive, returns None for a path outside of any archive
+pub fn split_virtual_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let mut archive = PathBuf::new();
+    let mut components = path.components();
+    for component in components.by_ref() {
+        if let Component::Normal(name) = component
+            && let Some(name) = name.to_str().and_then(|name| name.strip_suffix(VIRTUAL_ROOT_SUFFIX))
+            && ArchiveKind::detect(Path::new(name)).is_some() {
+            archive.push(name);
+            return Some((archive, components.as_path().to_path_buf()));
+        }
+        archive.push(component);
+    }
+    None
+}
+
+/// Returns true if the folder exists, for a virtual path only the archive file is checked
+pub fn folder_exists(path: &Path) -> bool {
+    match split_virtual_path(path) {
+        Some((archive, _)) => archive.is_file(),
+        None => path.is_dir(),
+    }
+}
+
+/// Normalizes a path stored in an archive, the leading `./` and `/`, the `..` components and the trailing `/` are removed
+/// so a browsed entry never points outside of the archive root
+fn normalize(path: &Path) -> String {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the path of an entry relative to the destination of an extraction, None if it could escape the destination
+/// (absolute paths and `..` components)
+pub fn safe_relative(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {},
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(relative)
+}
+
+/// Returns true if the target of a link stored at the relative path points outside of the archive root, the links of the archive are not
+/// followed so the extraction checks the target again with [`resolves_outside`]
+fn escapes(relative: &Path, target: &Path) -> bool {
+    if target.is_absolute() {
+        return true;
+    }
+    let mut depth = relative.parent().map(|parent| parent.components().count()).unwrap_or(0) as isize;
+    for component in target.components() {
+        match component {
+            Component::ParentDir => depth -= 1,
+            Component::Normal(_) => depth += 1,
+            _ => {},
+        }
+        if depth < 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns true if the target of a link created in the folder resolves outside of the root, the folder is canonical and the links
+/// met along the target are followed so a chain of extracted links can't lead out of the root
+fn resolves_outside(root: &Path, folder: &Path, target: &Path) -> bool {
+    if target.is_absolute() {
+        return true;
+    }
+    let mut resolved = folder.to_path_buf();
+    for component in target.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            },
+            Component::Normal(name) => {
+                resolved.push(name);
+                if fs::symlink_metadata(&resolved).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                    match fs::canonicalize(&resolved) {
+                        Ok(canonical) => resolved = canonical,
+                        // a dangling link can't be checked
+                        Err(_) => return true,
+                    }
+                }
+            },
+            _ => {},
+        }
+        if !resolved.starts_with(root) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Wraps the file in the decompressor of a tarball
+fn tar_reader(file: fs::File, kind: ArchiveKind) -> io::Result<Box<dyn Read>> {
+    match kind {
+        ArchiveKind::Tar => Ok(Box::new(file)),
+        ArchiveKind::TarGz => Ok(Box::new(GzDecoder::new(file))),
+        ArchiveKind::TarXz => Ok(Box::new(XzDecoder::new(file))),
+        ArchiveKind::TarZst => Ok(Box::new(zstd::Decoder::new(file)?)),
+        ArchiveKind::Zip => Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a tarball")),
+    }
+}
+
+/// Lists the entries of the archive in the order they are stored
+pub fn list(path: &Path, kind: ArchiveKind) -> io::Result<Vec<ArchiveEntry>> {
+    let file = fs::File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => list_zip(file),
+        _ => list_tar(tar_reader(file, kind)?),
+    }
+}
+
+fn list_zip(file: fs::File) -> io::Result<Vec<ArchiveEntry>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        // the raw entry skips the decompression setup, only the central directory is needed
+        let entry = archive.by_index_raw(index).map_err(io::Error::other)?;
+        // zip timestamps have no time zone, they are stored in the local time of the archiver
+        let modified = entry.last_modified().and_then(|time| {
+            let naive = NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+                .and_hms_opt(time.hour() as u32, time.minute() as u32, time.second() as u32)?;
+            Local.from_local_datetime(&naive).earliest().map(SystemTime::from)
+        });
+        entries.push(ArchiveEntry {
+            path: normalize(Path::new(entry.name().map_err(io::Error::other)?.as_ref())),
+            is_dir: entry.is_dir(),
+            size: entry.size(),
+            mode: entry.unix_mode(),
+            modified,
+        });
+    }
+    Ok(entries)
+}
+
+fn list_tar<R: Read>(reader: R) -> io::Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let modified = header.mtime().ok().map(|mtime| SystemTime::UNIX_EPOCH + Duration::from_secs(mtime));
+        entries.push(ArchiveEntry {
+            path: normalize(&entry.path()?),
+            is_dir: header.entry_type().is_dir(),
+            size: header.size()?,
+            mode: header.mode().ok(),
+            modified,
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads at most `limit` bytes of the member of the archive, the member path is relative to the archive root
+pub fn read_entry(path: &Path, kind: ArchiveKind, member: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let file = fs::File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => read_zip_entry(file, member, limit),
+        _ => read_tar_entry(tar_reader(file, kind)?, member, limit),
+    }
+}
+
+fn read_zip_entry(file: fs::File, member: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    let member = normalize(member);
+    // the member is looked up in the central directory, a name stored with a leading `./` or `/` is only found by comparing the normalized names
+    let index = archive.index_for_name(&member)
+        .or_else(|| archive.file_names().position(|name| name.is_ok_and(|name| normalize(Path::new(name.as_ref())) == member)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not in the archive", member)))?;
+    let entry = archive.by_index(index).map_err(io::Error::other)?;
+    let mut bytes = Vec::new();
+    entry.take(limit).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_tar_entry<R: Read>(reader: R, member: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(reader);
+    let member = normalize(member);
+    // tarballs have no index, the entries are decompressed in order until the member is found
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if normalize(&entry.path()?) == member {
+            let mut bytes = Vec::new();
+            entry.take(limit).read_to_end(&mut bytes)?;
+            return Ok(bytes);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not in the archive", member)))
+}
+
+/// Checks the path of every entry before anything is written and returns the total size of the files,
+/// an archive containing a path escaping the destination is rejected as a whole
+pub fn check_entries(path: &Path, kind: ArchiveKind) -> io::Result<u64> {
+    let file = fs::File::open(path)?;
+    let unsafe_entry = |name: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Unsafe entry path rejected: {}", name));
+    let mut total = 0;
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+            for index in 0..archive.len() {
+                let entry = archive.by_index_raw(index).map_err(io::Error::other)?;
+                let name = entry.name().map_err(io::Error::other)?;
+                if safe_relative(Path::new(name.as_ref())).is_none() {
+                    return Err(unsafe_entry(&name));
+                }
+                total += entry.size();
+            }
+        },
+        _ => {
+            let mut archive = tar::Archive::new(tar_reader(file, kind)?);
+            for entry in archive.entries()? {
+                let entry = entry?;
+                let path = entry.path()?;
+                let relative = safe_relative(&path).ok_or(unsafe_entry(&path.to_string_lossy()))?;
+                if let Some(target) = entry.link_name()?
+                    && escapes(&relative, &target) {
+                    return Err(unsafe_entry(&format!("{} -> {}", path.display(), target.display())));
+                }
+                total += entry.header().size()?;
+            }
+        },
+    }
+    Ok(total)
+}
+
+/// Extracts the archive into the destination folder, the archive must have been checked by [`check_entries`] first,
+/// existing files are handled by the conflict policy
+pub fn extract(path: &Path, kind: ArchiveKind, destination: &Path, policy: ConflictPolicy, progress: &mut dyn Progress) -> io::Result<ExtractSummary> {
+    fs::create_dir_all(destination)?;
+    let root = fs::canonicalize(destination)?;
+    let file = fs::File::open(path)?;
+    let mut summary = ExtractSummary::default();
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index).map_err(io::Error::other)?;
+                let name = entry.name().map_err(io::Error::other)?.to_string();
+                let relative = safe_relative(Path::new(&name)).ok_or(io::Error::new(io::ErrorKind::InvalidData, format!("Unsafe entry path rejected: {}", name)))?;
+                let target = prepare_target(&root, &relative, entry.is_dir())?;
+                if entry.is_dir() {
+                    continue;
+                }
+                // the links of a zip are stored as their target path, they are not restored
+                let resolved = if entry.is_symlink() { None } else { policy.resolve(&target) };
+                match resolved {
+                    Some(target) => {
+                        let mode = entry.unix_mode();
+                        write_file(&mut entry, &target, mode, progress)?;
+                        summary.extracted += 1;
+                    },
+                    None => {
+                        progress.advance(entry.size())?;
+                        summary.skipped += 1;
+                    },
+                }
+            }
+        },
+        _ => {
+            let mut archive = tar::Archive::new(tar_reader(file, kind)?);
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let path = entry.path()?.to_path_buf();
+                let relative = safe_relative(&path).ok_or(io::Error::new(io::ErrorKind::InvalidData, format!("Unsafe entry path rejected: {}", path.display())))?;
+                let entry_type = entry.header().entry_type();
+                let target = prepare_target(&root, &relative, entry_type.is_dir())?;
+                if entry_type.is_dir() {
+                    continue;
+                }
+                let size = entry.header().size()?;
+                match (entry_type.is_file(), entry_type.is_symlink(), policy.resolve(&target)) {
+                    (true, _, Some(target)) => {
+                        let mode = entry.header().mode().ok();
+                        write_file(&mut entry, &target, mode, progress)?;
+                        summary.extracted += 1;
+                    },
+                    (false, true, Some(target)) => {
+                        let link = entry.link_name()?.map(|link| link.to_path_buf()).unwrap_or_default();
+                        let folder = fs::canonicalize(target.parent().unwrap_or(&root))?;
+                        if resolves_outside(&root, &folder, &link) {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsafe link rejected: {} -> {}", path.display(), link.display())));
+                        }
+                        if fs::symlink_metadata(&target).is_ok() {
+                            fs::remove_file(&target)?;
+                        }
+                        symlink(&link, &target)?;
+                        summary.extracted += 1;
+                    },
+                    // hard links and special files are not restored
+                    _ => {
+                        progress.advance(size)?;
+                        summary.skipped += 1;
+                    },
+                }
+            }
+        },
+    }
+    Ok(summary)
+}
+
+/// Creates the parent folders of an entry (or the entry itself for a folder) one component at a time, a component that is a link or
+/// resolves outside of the destination root is refused as a link extracted before could redirect the entry outside of it
+fn prepare_target(root: &Path, relative: &Path, is_dir: bool) -> io::Result<PathBuf> {
+    let target = root.join(relative);
+    let folder = if is_dir { relative } else { relative.parent().unwrap_or(Path::new("")) };
+    let leaves = || io::Error::new(io::ErrorKind::InvalidData, format!("Entry path leaves the destination: {}", relative.display()));
+    let mut current = root.to_path_buf();
+    for component in folder.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return Err(leaves()),
+            Ok(_) => {},
+            Err(error) if error.kind() == io::ErrorKind::NotFound => fs::create_dir(&current)?,
+            Err(error) => return Err(error),
+        }
+        if !fs::canonicalize(&current)?.starts_with(root) {
+            return Err(leaves());
+        }
+    }
+    Ok(target)
+}
+
+fn write_file(reader: &mut dyn Read, target: &Path, mode: Option<u32>, progress: &mut dyn Progress) -> io::Result<()> {
+    transfer::replace_destination(target)?;
+    let mut file = fs::File::create(target)?;
+    copy(reader, &mut file, progress)?;
+    if let Some(mode) = mode {
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    Ok(())
+}
+
+/// Copies the reader into the writer, reporting the progress after each chunk
+pub fn copy(reader: &mut dyn Read, writer: &mut dyn Write, progress: &mut dyn Progress) -> io::Result<u64> {
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    let mut copied = 0;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+        progress.advance(read as u64)?;
+    }
+}
+
+/// Reader reporting the bytes read to a progress, used when the archive writer pulls the content itself
+struct ProgressReader<'a, R: Read> {
+    inner: R,
+    progress: &'a mut dyn Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buffer)?;
+        self.progress.advance(read as u64)?;
+        Ok(read)
+    }
+}
+
+/// File or folder to store in a new archive
+pub struct SourceEntry {
+    path: PathBuf,
+    name: String,
+    metadata: fs::Metadata,
+}
+
+/// Walks the sources to list what will be stored, each source is stored under its own name at the root of the archive,
+/// the links are stored as links and not followed
+pub fn collect_sources(sources: &[PathBuf]) -> io::Result<Vec<SourceEntry>> {
+    let mut entries = Vec::new();
+    let mut pending = sources.iter()
+        .filter_map(|source| Some((source.clone(), source.file_name()?.to_string_lossy().to_string())))
+        .collect::<Vec<(PathBuf, String)>>();
+    pending.reverse();
+    while let Some((path, name)) = pending.pop() {
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            let mut children = fs::read_dir(&path)?
+                .filter_map(|child| child.ok())
+                .map(|child| (child.path(), format!("{}/{}", name, child.file_name().to_string_lossy())))
+                .collect::<Vec<(PathBuf, String)>>();
+            children.sort_by(|first, second| second.1.cmp(&first.1));
+            pending.extend(children);
+        }
+        entries.push(SourceEntry { path, name, metadata });
+    }
+    Ok(entries)
+}
+
+/// Returns the total size of the files to store
+pub fn sources_size(entries: &[SourceEntry]) -> u64 {
+    entries.iter().filter(|entry| entry.metadata.is_file()).map(|entry| entry.metadata.len()).sum()
+}
+
+/// Creates the archive at the destination from the collected sources
+pub fn create(destination: &Path, kind: ArchiveKind, entries: &[SourceEntry], progress: &mut dyn Progress) -> io::Result<()> {
+    transfer::replace_destination(destination)?;
+    let file = fs::File::create(destination)?;
+    match kind {
+        ArchiveKind::Zip => create_zip(file, entries, progress),
+        ArchiveKind::Tar => create_tar(file, entries, progress).map(|_| ()),
+        ArchiveKind::TarGz => {
+            let encoder = create_tar(GzEncoder::new(file, flate2::Compression::default()), entries, progress)?;
+            encoder.finish().map(|_| ())
+        },
+        ArchiveKind::TarZst => {
+            let encoder = create_tar(zstd::Encoder::new(file, 0)?, entries, progress)?;
+            encoder.finish().map(|_| ())
+        },
+        ArchiveKind::TarXz => Err(io::Error::new(io::ErrorKind::Unsupported, "TAR.XZ archives can't be created")),
+    }
+}
+
+fn create_zip(file: fs::File, entries: &[SourceEntry], progress: &mut dyn Progress) -> io::Result<()> {
+    let mut writer = zip::ZipWriter::new(file);
+    for entry in entries {
+        let mut options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(entry.metadata.permissions().mode() & 0o777)
+            .large_file(entry.metadata.len() >= u32::MAX as u64);
+        // zip timestamps are stored in local time, the dates before 1980 can't be represented and keep the default one
+        if let Ok(modified) = entry.metadata.modified() {
+            let modified = DateTime::<Local>::from(modified);
+            if let Ok(time) = zip::DateTime::from_date_and_time(modified.year() as u16, modified.month() as u8, modified.day() as u8, modified.hour() as u8, modified.minute() as u8, modified.second() as u8) {
+                options = options.last_modified_time(time);
+            }
+        }
+        if entry.metadata.is_dir() {
+            writer.add_directory(entry.name.as_str(), options).map_err(io::Error::other)?;
+        }
+        else if entry.metadata.is_symlink() {
+            let target = fs::read_link(&entry.path)?;
+            writer.add_symlink(entry.name.as_str(), target.to_string_lossy(), options).map_err(io::Error::other)?;
+        }
+        else if entry.metadata.is_file() {
+            writer.start_file(entry.name.as_str(), options).map_err(io::Error::other)?;
+            copy(&mut fs::File::open(&entry.path)?, &mut writer, progress)?;
+        }
+    }
+    writer.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+fn create_tar<W: Write>(writer: W, entries: &[SourceEntry], progress: &mut dyn Progress) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&entry.metadata);
+        if entry.metadata.is_dir() {
+            builder.append_dir(&entry.name, &entry.path)?;
+        }
+        else if entry.metadata.is_symlink() {
+            let target = fs::read_link(&entry.path)?;
+            builder.append_link(&mut header, &entry.name, target)?;
+        }
+        else if entry.metadata.is_file() {
+            let reader = ProgressReader { inner: fs::File::open(&entry.path)?, progress: &mut *progress };
+            builder.append_data(&mut header, &entry.name, reader)?;
+        }
+    }
+    builder.into_inner()
+}
+
+/// Formats unix permission bits like `ls -l` does (e.g. `drwxr-xr-x`)
+pub fn mode_to_string(mode: u32, is_dir: bool) -> String {
+    let mut result = String::with_capacity(10);
+    result.push(if is_dir { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        result.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        result.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        result.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use crate::utility::temp::TempDir;
+    use super::{ArchiveKind, ConflictPolicy, NoProgress, mode_to_string, split_virtual_path, virtual_root, normalize, safe_relative, escapes, check_entries, extract, collect_sources, create};
+    #[test]
+    fn test_detect() {
+        assert_eq!(ArchiveKind::detect(Path::new("/tmp/a.zip")), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::detect(Path::new("a.TAR.GZ")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("a.tgz")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("a.tar.xz")), Some(ArchiveKind::TarXz));
+        assert_eq!(ArchiveKind::detect(Path::new("a.tar")), Some(ArchiveKind::Tar));
+        assert_eq!(ArchiveKind::detect(Path::new("a.tar.zst")), Some(ArchiveKind::TarZst));
+        assert_eq!(ArchiveKind::detect(Path::new("a.gz")), None);
+        assert_eq!(ArchiveKind::detect(Path::new("a.7z")), None);
+    }
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(ArchiveKind::TarGz.stem("src.TAR.GZ"), Some("src"));
+        // lowercasing changes the length of these names
+        assert_eq!(ArchiveKind::Zip.stem("\u{e9}\u{212a}.zip"), Some("\u{e9}\u{212a}"));
+        assert_eq!(ArchiveKind::TarGz.stem("\u{130}\u{130}\u{130}.tar.gz"), Some("\u{130}\u{130}\u{130}"));
+        assert_eq!(ArchiveKind::Zip.stem("\u{e9}abc"), None);
+        assert_eq!(ArchiveKind::TarGz.stem("a.tgz"), None);
+        assert_eq!(ArchiveKind::Zip.stem("zip"), None);
+    }
+
+    #[test]
+    fn test_split_virtual_path() {
+        assert_eq!(virtual_root(Path::new("/tmp/x.tar.gz")), PathBuf::from("/tmp/x.tar.gz!"));
+        assert_eq!(split_virtual_path(Path::new("/tmp/x.tar.gz!/src/lib")), Some((PathBuf::from("/tmp/x.tar.gz"), PathBuf::from("src/lib"))));
+        assert_eq!(split_virtual_path(Path::new("/tmp/x.zip!")), Some((PathBuf::from("/tmp/x.zip"), PathBuf::new())));
+        assert_eq!(split_virtual_path(Path::new("/tmp/x.tar.gz/src")), None);
+        assert_eq!(split_virtual_path(Path::new("/tmp/wow!/src")), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("./src/lib/")), "src/lib");
+        assert_eq!(normalize(Path::new("/src/../../etc/passwd")), "src/etc/passwd");
+    }
+
+    #[test]
+    fn test_safe_relative() {
+        assert_eq!(safe_relative(Path::new("./src/lib.rs")), Some(PathBuf::from("src/lib.rs")));
+        assert_eq!(safe_relative(Path::new("src/../../etc/passwd")), None);
+        assert_eq!(safe_relative(Path::new("/etc/passwd")), None);
+        assert!(!escapes(Path::new("a/b/link"), Path::new("../c")));
+        assert!(escapes(Path::new("a/link"), Path::new("../../c")));
+        assert!(escapes(Path::new("link"), Path::new("/etc")));
+    }
+
+    /// Writes a tarball of links and files holding their name
+    fn tarball(path: &Path, links: &[(&str, &str)], files: &[&str]) {
+        let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+        for (link, target) in links {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, link, target).unwrap();
+        }
+        for file in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(file.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, file, file.as_bytes()).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_create_over_link() {
+        let root = TempDir::new("create");
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("outside.txt"), "outside").unwrap();
+        std::os::unix::fs::symlink(root.join("outside.txt"), root.join("a.zip")).unwrap();
+        // an overwritten link is replaced by the archive, the file it points to is left alone
+        let entries = collect_sources(&[root.join("a.txt")]).unwrap();
+        create(&root.join("a.zip"), ArchiveKind::Zip, &entries, &mut NoProgress).unwrap();
+        assert!(!fs::symlink_metadata(root.join("a.zip")).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(root.join("outside.txt")).unwrap(), "outside");
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let root = TempDir::new("extract");
+        let archive = root.join("links.tar");
+        let destination = root.join("out");
+        // each link stays inside the destination on its own, together they lead out of it
+        tarball(&archive, &[("d/up", ".."), ("d/up/x", "../..")], &["d/up/x/newdir/f"]);
+        assert!(check_entries(&archive, ArchiveKind::Tar).is_ok());
+        assert!(extract(&archive, ArchiveKind::Tar, &destination, ConflictPolicy::Skip, &mut NoProgress).is_err());
+        assert!(fs::symlink_metadata(destination.join("x")).is_err());
+        assert!(!root.parent().unwrap().join("newdir").exists());
+
+        tarball(&archive, &[("d/up", ".."), ("x", "d/up/..")], &[]);
+        assert!(check_entries(&archive, ArchiveKind::Tar).is_ok());
+        let error = extract(&archive, ArchiveKind::Tar, &destination, ConflictPolicy::Overwrite, &mut NoProgress).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(fs::symlink_metadata(destination.join("x")).is_err());
+
+        let _ = fs::remove_dir_all(&destination);
+        tarball(&archive, &[("d/link", "../f.txt")], &["f.txt"]);
+        let summary = extract(&archive, ArchiveKind::Tar, &destination, ConflictPolicy::Skip, &mut NoProgress).unwrap();
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(fs::read_to_string(destination.join("d/link")).unwrap(), "f.txt");
+    }
+
+    #[test]
+    fn test_mode_to_string() {
+        assert_eq!(mode_to_string(0o755, true), "drwxr-xr-x");
+        assert_eq!(mode_to_string(0o100644, false), "-rw-r--r--");
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::archive;
+use crate::utility::path::{config_dir, data_dir};
+
+// Name of the file storing the bookmarks in the data folder
+static BOOKMARKS_FILE: &str = "bookmarks";
+
+/// Folder saved by the user, the ones with a key can be jumped to directly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub key: Option<char>,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Bookmark {
+    /// Returns true if the folder still exists, for an archive path the archive file must exist
+    pub fn exists(&self) -> bool {
+        archive::folder_exists(&self.path)
+    }
+
+    /// Parses a line of the bookmarks file: the key (empty when none), the name and the path separated by tabulations
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let key = fields.next()?.chars().next();
+        let name = fields.next()?.to_string();
+        let path = PathBuf::from(fields.next()?);
+        Some(Self { key, name, path })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.key.map(String::from).unwrap_or_default(), self.name, self.path.to_string_lossy())
+    }
+}
+
+/// Bookmarks of the user, saved in the data folder after each change
+pub struct Bookmarks {
+    entries: Vec<Bookmark>,
+    file: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks file, a missing or unreadable file gives no bookmarks
+    pub fn load() -> Self {
+        let file = data_dir().map(|folder| folder.join(BOOKMARKS_FILE));
+        let entries = file.as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| content.lines().filter_map(Bookmark::parse).collect())
+            .unwrap_or_default();
+        Self { entries, file }
+    }
+
+    pub fn entries(&self) -> &Vec<Bookmark> {
+        &self.entries
+    }
+
+    pub fn get(&self, key: char) -> Option<&Bookmark> {
+        self.entries.iter().find(|bookmark| bookmark.key == Some(key))
+    }
+
+    /// Binds the key to the path, replacing the bookmark previously bound to it
+    pub fn set(&mut self, key: char, path: &Path) -> io::Result<()> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(path.to_string_lossy().to_string());
+        self.entries.retain(|bookmark| bookmark.key != Some(key));
+        self.entries.push(Bookmark { key: Some(key), name, path: path.to_path_buf() });
+        self.entries.sort_by_key(|bookmark| (bookmark.key.is_none(), bookmark.key));
+        self.save()
+    }
+
+    pub fn remove(&mut self, index: usize) -> io::Result<()> {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+        self.save()
+    }
+
+    /// Imports the GTK bookmarks (`~/.config/gtk-3.0/bookmarks`) as bookmarks without key, the paths already bookmarked are skipped,
+    /// returns the number of imported bookmarks
+    pub fn import_gtk(&mut self) -> io::Result<usize> {
+        let file = config_dir().map(|folder| folder.join("gtk-3.0").join("bookmarks"))
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "No configuration folder"))?;
+        let content = fs::read_to_string(file)?;
+        let mut imported = 0;
+        for bookmark in content.lines().filter_map(parse_gtk_line) {
+            if self.entries.iter().all(|entry| entry.path != bookmark.path) {
+                self.entries.push(bookmark);
+                imported += 1;
+            }
+        }
+        self.save()?;
+        Ok(imported)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let file = self.file.as_ref().ok_or(io::Error::new(io::ErrorKind::NotFound, "No data folder"))?;
+        if let Some(folder) = file.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let content = self.entries.iter().map(|bookmark| bookmark.to_line() + "\n").collect::<String>();
+        fs::write(file, content)
+    }
+}
+
+/// Parses a line of the GTK bookmarks file, a `file://` URI optionally followed by a label, the other URIs are ignored
+fn parse_gtk_line(line: &str) -> Option<Bookmark> {
+    let (uri, label) = match line.split_once(' ') {
+        Some((uri, label)) => (uri, Some(label.trim())),
+        None => (line.trim(), None),
+    };
+    let path = PathBuf::from(percent_decode(uri.strip_prefix("file://")?));
+    let name = match label {
+        Some(label) if !label.is_empty() => label.to_string(),
+        _ => path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(path.to_string_lossy().to_string()),
+    };
+    Some(Bookmark { key: None, name, path })
+}
+
+/// Decodes the `%XX` escapes of an URI, the invalid escapes are kept as is
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| value.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use super::{Bookmark, parse_gtk_line, percent_decode};
+    #[test]
+    fn test_bookmark_line() {
+        let bookmark = Bookmark { key: Some('p'), name: "projects".to_string(), path: PathBuf::from("/home/me/my projects") };
+        assert_eq!(Bookmark::parse(&bookmark.to_line()), Some(bookmark));
+        assert_eq!(Bookmark::parse("\tdocs\t/home/me/docs").map(|bookmark| bookmark.key), Some(None));
+        assert_eq!(Bookmark::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_parse_gtk_line() {
+        assert_eq!(percent_decode("/a%20b/%C3%A9t%C3%A9%2"), "/a b/été%2");
+        let bookmark = parse_gtk_line("file:///home/me/My%20Music Music").unwrap();
+        assert_eq!((bookmark.name.as_str(), bookmark.path), ("Music", PathBuf::from("/home/me/My Music")));
+        assert_eq!(parse_gtk_line("file:///tmp").map(|bookmark| bookmark.name), Some("tmp".to_string()));
+        assert_eq!(parse_gtk_line("sftp://server/home"), None);
+    }
+}
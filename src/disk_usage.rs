@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use crate::archive::Cancelled;
+
+/// Space taken by a folder and everything under it
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DiskUsage {
+    // sum of the lengths of the entries
+    pub apparent: u64,
+    // blocks allocated on the disk for the entries
+    pub disk: u64,
+    // number of entries that are not folders
+    pub files: u64,
+}
+
+/// Measures the folder and everything under it, links are not followed and a file with several hard links is counted once.
+/// The folders on another filesystem are skipped unless `cross_filesystems`, the unreadable subfolders are skipped
+pub fn measure(root: &Path, cross_filesystems: bool, cancel: &dyn Fn() -> bool) -> io::Result<DiskUsage> {
+    let metadata = fs::symlink_metadata(root)?;
+    let mut measurer = Measurer { device: metadata.dev(), cross_filesystems, cancel, linked: HashSet::new(), usage: DiskUsage::default() };
+    measurer.add(&metadata);
+    if metadata.is_dir() {
+        measurer.measure_folder(root)?;
+    }
+    Ok(measurer.usage)
+}
+
+struct Measurer<'a> {
+    // filesystem of the measured folder
+    device: u64,
+    cross_filesystems: bool,
+    cancel: &'a dyn Fn() -> bool,
+    // device and inode of the hard linked files already counted
+    linked: HashSet<(u64, u64)>,
+    usage: DiskUsage,
+}
+
+impl Measurer<'_> {
+    fn measure_folder(&mut self, folder: &Path) -> io::Result<()> {
+        if (self.cancel)() {
+            return Err(io::Error::other(Cancelled));
+        }
+        for entry in fs::read_dir(folder)?.filter_map(Result::ok) {
+            // the metadata of an entry is the one of the link itself
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() && !self.cross_filesystems && metadata.dev() != self.device {
+                continue;
+            }
+            self.add(&metadata);
+            if metadata.is_dir() && self.measure_folder(&entry.path()).is_err() && (self.cancel)() {
+                return Err(io::Error::other(Cancelled));
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, metadata: &Metadata) {
+        if !metadata.is_dir() && metadata.nlink() > 1 && !self.linked.insert((metadata.dev(), metadata.ino())) {
+            return;
+        }
+        self.usage.apparent += metadata.len();
+        self.usage.disk += metadata.blocks() * 512;
+        if !metadata.is_dir() {
+            self.usage.files += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use crate::utility::temp::TempDir;
+    use super::measure;
+
+    #[test]
+    fn test_measure() {
+        let root = TempDir::new("usage");
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/x.bin"), vec![0; 1000]).unwrap();
+        fs::write(root.join("y.bin"), vec![0; 24]).unwrap();
+        // a hard link is counted once and a symbolic link is not followed
+        fs::hard_link(root.join("a/x.bin"), root.join("x_link.bin")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("a_link")).unwrap();
+        let folders = fs::symlink_metadata(&root).unwrap().len() + fs::symlink_metadata(root.join("a")).unwrap().len();
+        let link = fs::symlink_metadata(root.join("a_link")).unwrap().len();
+
+        let usage = measure(&root, false, &|| false).unwrap();
+        assert_eq!(usage.files, 3);
+        assert_eq!(usage.apparent, folders + 1024 + link);
+        assert!(usage.disk > 0);
+        assert!(measure(&root, false, &|| true).is_err());
+    }
+}
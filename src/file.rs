@@ -15,12 +15,28 @@ pub struct File {
     access_time: SystemTime,
     creation_time: SystemTime,
     size: Size,
+    bytes: u64,
 }
 
 impl File {
+    /// Builds a file that doesn't come from a folder of the local filesystem (e.g. an archive entry)
+    pub fn new(name: String, file_type: FileType, modified_time: SystemTime, bytes: u64) -> Self {
+        Self { name, file_type, modified_time, access_time: modified_time, creation_time: modified_time, size: bytes.into(), bytes }
+    }
+
+    /// Renames the file, the rows of the tree view are named by their path relative to the listed folder
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
+    /// Returns the last component of the name, the name itself for a file of the listed folder
+    pub fn base_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or(&self.name)
+    }
     pub fn file_type(&self) -> &FileType {
         &self.file_type
     }
@@ -36,6 +52,12 @@ impl File {
     pub fn size(&self) -> &Size {
         &self.size
     }
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
     pub fn is_file(&self) -> bool {
         matches!(self.file_type, FileType::File)
     }
@@ -62,10 +84,12 @@ impl File {
         self.size.to_string()
     }
 
-    pub fn to_line(&self, max_name_width: usize, max_modified_width: usize) -> Line<'_> {
-        let name = expand_or_truncate(self.name.clone(), max_name_width);
+    /// Builds the row of the file, the label is displayed in place of the name
+    pub fn to_line(&self, label: String, size: String, max_name_width: usize, max_size_width: usize, max_modified_width: usize) -> Line<'_> {
+        let name = expand_or_truncate(label, max_name_width);
+        let size = expand_or_truncate(size, max_size_width);
         let modified = expand_or_truncate(self.modified_time_to_string(), max_modified_width);
-        Line::from(format!("{}{}", name, modified))
+        Line::from(format!("{}{}{}", name, size, modified))
     }
 }
 
@@ -81,6 +105,7 @@ impl TryFrom<DirEntry> for File {
             access_time: metadata.accessed()?,
             creation_time: metadata.created()?,
             size: metadata.len().into(),
+            bytes: metadata.len(),
         })
     }
 }
@@ -1,25 +1,124 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::rc::Rc;
+use std::cell::{Ref, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
-use crate::file::{File, FileType};
+use crate::file::{File, FileType, Size};
 use std::sync::mpsc;
 use crate::workers::LightWorkerMessage;
 use crate::workers::LightWorkerAction;
 use std::sync::mpsc::SendError;
 use crate::workers::LightWorkerResponse;
-use opener;
+use crate::workers::ListingOptions;
+use crate::workers::{LoadRequest, Listing, CacheStats, ArchiveSource, LightServiceError};
+use crate::workers::{WatcherMessage, WatcherResponse};
+use crate::workers::ReadOptions;
+use crate::workers::{HeavyWorkerMessage, HeavyWorkerAction, HeavyWorkerResponse};
+use crate::workers::{ExtractJob, CompressJob, TransferJob, WalkJob, MeasureJob};
+use crate::disk_usage::DiskUsage;
+use crate::transfer::TransferMode;
+use crate::utility::path::expand;
+use crate::preview::Preview;
+use crate::archive::{self, ArchiveKind, ConflictPolicy};
+use crate::bookmarks::Bookmarks;
+use crate::frecency::Frecency;
+use encoding_rs::Encoding;
+
+// Max number of folders kept in the navigation history
+static MAX_HISTORY: usize = 100;
+// Max number of folder listings kept for the parent column of the miller columns
+static MAX_PARENT_LISTINGS: usize = 32;
+// Max depth of a recursive listing
+static MAX_WALK_DEPTH: usize = 64;
+// Cells size of the preview section used until the first frame is rendered
+static DEFAULT_PREVIEW_AREA: (u16, u16) = (80, 24);
 
 pub struct FileManager {
     path: PathBuf,
+    // rows of the list: the folder listing with the expanded subfolders inlined, named by their relative path
     files: Vec<File>,
-    selected_file_preview_buffer: String,
+    // tree guides drawn before the name of each row
+    guides: Vec<String>,
+    listing: Vec<File>,
+    // listings of the expanded subfolders by path relative to the folder, kept on a reload
+    subtrees: HashMap<PathBuf, Vec<File>>,
+    // recursive listing displayed in place of the folder listing
+    walk: Option<Walk>,
+    selected_file_preview: Preview,
+    listing_options: ListingOptions,
+    pending_selection: Option<String>,
+    // last selected file name and index of each visited folder
+    cursors: HashMap<PathBuf, (String, usize)>,
+    history: Vec<HistoryEntry>,
+    history_index: usize,
+    // history index targeted by the back or forward load in flight
+    pending_history: Option<usize>,
+    selected: Option<usize>,
+    show_parent: bool,
+    // listings of the loaded folders by path, the parent column reads the listing of the current folder parent
+    parent_listings: HashMap<PathBuf, Vec<File>>,
+    forced_encoding: Option<(PathBuf, &'static Encoding)>,
+    preview_area: (u16, u16),
+    marked: HashSet<String>,
+    jobs: Vec<Job>,
+    next_job_id: usize,
+    status: Option<String>,
+    // shared by the file managers of all the tabs
+    bookmarks: Rc<RefCell<Bookmarks>>,
+    frecency: Rc<RefCell<Frecency>>,
+    // a reload requested by the watcher is in flight, and the last load was one
+    refreshing: bool,
+    // the folder is still coming by batches, its listing grows until it's loaded
+    loading: bool,
+    // usage of the listing cache sent with the last listing
+    cache_stats: CacheStats,
+    // total sizes of the folders by path, measured on demand or automatically for the folders of each listing
+    sizes: HashMap<PathBuf, FolderSize>,
+    measures: Vec<Measure>,
+    auto_measure: bool,
+    cross_filesystems: bool,
+    // the size column shows the blocks the folders take on the disk rather than the length of their content
+    disk_usage: bool,
+    refreshed: bool,
+    // path of the previewed file read again after a change
+    rereading: Option<PathBuf>,
+    // folder of an archive loaded once the heavy worker has indexed the archive
+    indexing: Option<PathBuf>,
+    // the text previews show their last lines, like `tail -f`
+    follow: bool,
     light_sync_id: usize,
     light_worker_channel: mpsc::Sender<LightWorkerMessage>,
+    heavy_worker_channel: mpsc::Sender<HeavyWorkerMessage>,
+    // without watcher (inotify unavailable) the listing is only refreshed on a reload
+    watcher_channel: Option<mpsc::Sender<WatcherMessage>>,
 }
 // public methods
 impl FileManager {
-    pub fn new(path :&PathBuf, light_sync_id: usize, light_worker_channel: mpsc::Sender<LightWorkerMessage>) -> Self {
-        Self { path: path.clone(), files: Vec::new(), selected_file_preview_buffer: String::new(), light_sync_id, light_worker_channel }
+    pub fn new(path :&Path, light_sync_id: usize, light_worker_channel: mpsc::Sender<LightWorkerMessage>, heavy_worker_channel: mpsc::Sender<HeavyWorkerMessage>) -> Self {
+        Self { path: path.to_path_buf(), files: Vec::new(), guides: Vec::new(), listing: Vec::new(), subtrees: HashMap::new(), walk: None, selected_file_preview: Preview::Empty, listing_options: ListingOptions::default(), pending_selection: None, cursors: HashMap::new(), history: Vec::new(), history_index: 0, pending_history: None, selected: None, show_parent: false, parent_listings: HashMap::new(), forced_encoding: None, preview_area: DEFAULT_PREVIEW_AREA, marked: HashSet::new(), jobs: Vec::new(), next_job_id: 0, status: None, bookmarks: Rc::new(RefCell::new(Bookmarks::load())), frecency: Rc::new(RefCell::new(Frecency::load())), refreshing: false, loading: false, cache_stats: CacheStats::default(), sizes: HashMap::new(), measures: Vec::new(), auto_measure: false, cross_filesystems: false, disk_usage: false, refreshed: false, rereading: None, indexing: None, follow: false, light_sync_id, light_worker_channel, heavy_worker_channel, watcher_channel: None }
+    }
+
+    /// Creates the file manager of a new tab on the path, it shares the bookmarks and the visited folders and keeps the listing options
+    pub fn new_tab(&self, path: &Path, light_worker_channel: mpsc::Sender<LightWorkerMessage>, heavy_worker_channel: mpsc::Sender<HeavyWorkerMessage>) -> Self {
+        let mut file_manager = Self::new(path, 0, light_worker_channel, heavy_worker_channel);
+        file_manager.listing_options = self.listing_options;
+        file_manager.bookmarks = Rc::clone(&self.bookmarks);
+        file_manager.frecency = Rc::clone(&self.frecency);
+        file_manager.follow = self.follow;
+        file_manager
+    }
+
+    /// Creates the file manager of a copy of this tab, with the same history and the same file selected
+    pub fn duplicate(&self, light_worker_channel: mpsc::Sender<LightWorkerMessage>, heavy_worker_channel: mpsc::Sender<HeavyWorkerMessage>) -> Self {
+        let mut file_manager = self.new_tab(&self.path, light_worker_channel, heavy_worker_channel);
+        file_manager.history = self.history.clone();
+        file_manager.history_index = self.history_index;
+        file_manager.cursors = self.cursors.clone();
+        file_manager.pending_selection = self.selected_file().map(|file| file.name().to_string());
+        file_manager
     }
 
     pub fn path(&self) -> &PathBuf {
@@ -30,14 +129,152 @@ impl FileManager {
         &self.files
     }
 
-    pub fn selected_file_preview_buffer(&self) -> &str {
-        &self.selected_file_preview_buffer
+    /// Returns the text displayed as the name of the row: the tree guides then the file name,
+    /// or the path relative to the folder in a recursive listing
+    pub fn label(&self, index: usize) -> String {
+        if self.walk.is_some() {
+            return self.files.get(index).map(|file| file.name().to_string()).unwrap_or_default();
+        }
+        let guide = self.guides.get(index).map(String::as_str).unwrap_or_default();
+        format!("{}{}", guide, self.files.get(index).map(File::base_name).unwrap_or_default())
+    }
+
+    /// Returns the text of the size column for the file at the index, a folder shows its total size once measured,
+    /// either its apparent size or its usage on the disk
+    pub fn size_label(&self, index: usize) -> String {
+        let Some(file) = self.files.get(index) else {
+            return String::new();
+        };
+        if !file.is_folder() {
+            return file.size().to_string();
+        }
+        match self.sizes.get(&self.path.join(file.name())) {
+            Some(FolderSize::Measuring(_)) => String::from("..."),
+            Some(FolderSize::Measured(usage)) => Size::from(if self.disk_usage { usage.disk } else { usage.apparent }).to_string(),
+            Some(FolderSize::Failed) => String::from("?"),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the index of the file whose content was last requested
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn selected_file_preview(&self) -> &Preview {
+        &self.selected_file_preview
+    }
+
+    pub fn selected_file_preview_mut(&mut self) -> &mut Preview {
+        &mut self.selected_file_preview
+    }
+
+    /// Sets the cells size of the preview section, the images are downscaled to it
+    pub fn set_preview_area(&mut self, width: u16, height: u16) {
+        self.preview_area = (width, height);
+    }
+
+    /// Returns the file whose content was last requested, if any
+    pub fn selected_file(&self) -> Option<&File> {
+        self.selected.and_then(|index| self.files.get(index))
+    }
+
+    /// Returns true if the file of the current folder is marked for a multi-file action
+    pub fn is_marked(&self, name: &str) -> bool {
+        self.marked.contains(name)
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    /// Returns the depth of the recursive listing when one is displayed
+    pub fn walk_depth(&self) -> Option<usize> {
+        self.walk.as_ref().map(|walk| walk.depth)
+    }
+
+    /// Returns the background jobs still running
+    pub fn jobs(&self) -> &Vec<Job> {
+        &self.jobs
+    }
+
+    /// Returns the message left by the last finished job, if any
+    pub fn status(&self) -> Option<&String> {
+        self.status.as_ref()
+    }
+
+    /// Returns the listing of the parent folder with the index of the current folder in it, when the miller columns are shown
+    pub fn parent_listing(&self) -> Option<(&Vec<File>, Option<usize>)> {
+        let files = self.parent_listings.get(self.path.parent()?)?;
+        let name = self.name_in_parent();
+        Some((files, files.iter().position(|file| Some(file.name()) == name.as_deref())))
+    }
+
+    pub fn bookmarks(&self) -> Ref<'_, Bookmarks> {
+        self.bookmarks.borrow()
+    }
+
+    pub fn frecency(&self) -> Ref<'_, Frecency> {
+        self.frecency.borrow()
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    /// Returns the index to select once a folder is loaded: the file requested by the last action if any,
+    /// then the file last selected in this folder or the entry now at its index if it's gone, the first file otherwise
+    pub fn take_selection_after_load(&mut self) -> Option<usize> {
+        if self.files.is_empty() {
+            if !self.loading {
+                self.pending_selection = None;
+            }
+            return None;
+        }
+        let position = |name: &str| self.files.iter().position(|file| file.name() == name);
+        // the file may come in a later batch of a big folder
+        let pending = match self.loading {
+            true => self.pending_selection.clone(),
+            false => self.pending_selection.take(),
+        };
+        if let Some(index) = pending.and_then(|name| position(&name)) {
+            self.pending_selection = None;
+            return Some(index);
+        }
+        match self.cursors.get(&self.path) {
+            Some((name, index)) => position(name).or(Some((*index).min(self.files.len() - 1))),
+            None => Some(0),
+        }
+    }
+
+    /// Returns the index of the file asked for by the navigation once its batch came in, it's given up when the folder is loaded without it
+    pub fn take_pending_selection(&mut self) -> Option<usize> {
+        let index = self.pending_selection.as_ref().and_then(|name| self.files.iter().position(|file| file.name() == name));
+        if index.is_some() || !self.loading {
+            self.pending_selection = None;
+        }
+        index
     }
 
     pub fn light_sync_id(&self) -> usize {
         self.light_sync_id
     }
 
+    /// Forgets the file and the history entry to select in the folder that failed to load, they would be applied to the next one otherwise.
+    /// A folder of an archive that is not indexed keeps them, it's loaded again once the heavy worker has indexed the archive
+    pub fn consume_load_error(&mut self, error: LightServiceError) {
+        self.increment_light_sync_id();
+        if let LightServiceError::NotIndexed(path) = error
+            && let Some((archive, _)) = archive::split_virtual_path(&path) {
+            self.status = Some(format!("Indexing {}...", archive.display()));
+            self.indexing = Some(path);
+            let _ = self.heavy_worker_channel.send(HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::IndexArchive(archive)));
+            return;
+        }
+        self.pending_selection = None;
+        self.pending_history = None;
+    }
+
     pub fn increment_light_sync_id(&mut self) {
         self.light_sync_id = (self.light_sync_id + 1) % usize::MAX;
     }
@@ -46,16 +283,156 @@ impl FileManager {
         self.light_worker_channel = channel;
     }
 
+    pub fn set_heavy_worker_channel(&mut self, channel: mpsc::Sender<HeavyWorkerMessage>) {
+        self.heavy_worker_channel = channel;
+    }
+
+    pub fn set_watcher_channel(&mut self, channel: mpsc::Sender<WatcherMessage>) {
+        self.watcher_channel = Some(channel);
+    }
+
+    /// Returns true if the last load was a refresh of the folder after a change on the disk
+    pub fn was_refreshed(&self) -> bool {
+        self.refreshed
+    }
+
+    /// Returns true if the selected file changed and was not read again yet
+    pub fn has_pending_reread(&self) -> bool {
+        self.rereading.is_some()
+    }
+
+    pub fn follows(&self) -> bool {
+        self.follow
+    }
+
     pub fn shutdown(&self) {
         let _ = self.light_worker_channel.send(LightWorkerMessage::Shutdown);
+        let _ = self.heavy_worker_channel.send(HeavyWorkerMessage::Shutdown);
+        if let Some(channel) = &self.watcher_channel {
+            let _ = channel.send(WatcherMessage::Shutdown);
+        }
     }
 
     pub fn dispatch(&mut self, action: FileManagerAction) -> Result<(), FileManagerError> {
         match action {
             FileManagerAction::Open(index) => self.open(index),
             FileManagerAction::GoToParent => self.go_to_parent(),
+            FileManagerAction::GoBack => self.go_to_history(self.history_index.checked_sub(1)),
+            FileManagerAction::GoForward => self.go_to_history(Some(self.history_index + 1)),
             FileManagerAction::Reload => self.reload_files(),
             FileManagerAction::ReadContent(index) => self.read_content(index),
+            FileManagerAction::JumpToLinkTarget(index) => self.jump_to_link_target(index),
+            FileManagerAction::ForceEncoding(encoding) => self.force_encoding(encoding),
+            FileManagerAction::ToggleHidden => {
+                self.listing_options.show_hidden = !self.listing_options.show_hidden;
+                self.parent_listings.clear();
+                self.reload_files()
+            },
+            FileManagerAction::ToggleFollow => {
+                self.follow = !self.follow;
+                self.status = Some(format!("Follow mode {}", if self.follow { "ON" } else { "OFF" }));
+                // the file is read again to be shown from its end
+                self.rereading = self.selected_file().map(|file| self.path.join(file.name()));
+                self.read_content(self.selected)
+            },
+            FileManagerAction::ShowCacheStats => {
+                self.status = Some(self.cache_stats.to_string());
+                Ok(())
+            },
+            FileManagerAction::ToggleMark(index) => {
+                if let Some(file) = self.files.get(index) && !self.marked.remove(file.name()) {
+                    self.marked.insert(file.name().to_string());
+                }
+                Ok(())
+            },
+            FileManagerAction::Extract{index, folder, policy} => self.extract(index, folder, policy),
+            FileManagerAction::Compress{index, name, kind, policy} => self.compress(index, name, kind, policy),
+            FileManagerAction::Transfer{index, destination, mode, policy} => self.transfer(index, destination, mode, policy),
+            FileManagerAction::CancelJobs => {
+                self.jobs.iter().for_each(|job| job.cancel.store(true, Ordering::Relaxed));
+                if let Some(walk) = &self.walk {
+                    walk.cancel.store(true, Ordering::Relaxed);
+                }
+                self.measures.iter().for_each(|measure| measure.cancel.store(true, Ordering::Relaxed));
+                Ok(())
+            },
+            FileManagerAction::MeasureFolders(index) => {
+                let folders = self.sources(index).into_iter().filter(|path| fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())).collect::<Vec<PathBuf>>();
+                self.marked.clear();
+                self.measure(folders)
+            },
+            FileManagerAction::ToggleAutoMeasure => {
+                self.auto_measure = !self.auto_measure;
+                self.status = Some(format!("Automatic folder sizes {}", if self.auto_measure { "ON" } else { "OFF" }));
+                match self.auto_measure {
+                    true => self.measure_listing(),
+                    false => Ok(()),
+                }
+            },
+            FileManagerAction::ToggleCrossFilesystems => {
+                self.cross_filesystems = !self.cross_filesystems;
+                self.status = Some(format!("Folder sizes {} other filesystems", if self.cross_filesystems { "cross" } else { "don't cross" }));
+                // the sizes measured with the other rule are dropped, the automatic sizes are measured again
+                self.measures.drain(..).for_each(|measure| measure.cancel.store(true, Ordering::Relaxed));
+                self.sizes.clear();
+                match self.auto_measure {
+                    true => self.measure_listing(),
+                    false => Ok(()),
+                }
+            },
+            FileManagerAction::ToggleDiskUsage => {
+                self.disk_usage = !self.disk_usage;
+                self.status = Some(format!("Folder sizes show the {}", if self.disk_usage { "usage on the disk" } else { "apparent size" }));
+                Ok(())
+            },
+            FileManagerAction::OpenPath(path) => self.open_path(path),
+            FileManagerAction::ListRecursively(depth) => self.list_recursively(depth),
+            FileManagerAction::Expand(index) => self.expand(index),
+            FileManagerAction::Collapse(index) => {
+                self.collapse(index);
+                Ok(())
+            },
+            FileManagerAction::ShowParent(show) => {
+                if self.show_parent == show {
+                    return Ok(());
+                }
+                self.show_parent = show;
+                self.parent_listings.clear();
+                match show {
+                    true => self.reload_files(),
+                    false => Ok(()),
+                }
+            },
+            FileManagerAction::SetBookmark(key) => {
+                self.status = Some(match self.bookmarks.borrow_mut().set(key, &self.path) {
+                    Ok(()) => format!("Bookmark '{}' set to {}", key, self.path.display()),
+                    Err(error) => format!("Failed to save the bookmarks: {}", error),
+                });
+                Ok(())
+            },
+            FileManagerAction::JumpToBookmark(key) => {
+                let path = self.bookmarks.borrow().get(key).map(|bookmark| bookmark.path.clone());
+                match path {
+                    Some(path) => self.open_path(path),
+                    None => {
+                        self.status = Some(format!("No bookmark on '{}'", key));
+                        Ok(())
+                    },
+                }
+            },
+            FileManagerAction::DeleteBookmark(index) => {
+                if let Err(error) = self.bookmarks.borrow_mut().remove(index) {
+                    self.status = Some(format!("Failed to save the bookmarks: {}", error));
+                }
+                Ok(())
+            },
+            FileManagerAction::ImportGtkBookmarks => {
+                self.status = Some(match self.bookmarks.borrow_mut().import_gtk() {
+                    Ok(imported) => format!("{} GTK bookmarks imported", imported),
+                    Err(error) => format!("Failed to import the GTK bookmarks: {}", error),
+                });
+                Ok(())
+            },
             FileManagerAction::CreateFolder(_relative_path) => {
                 todo!();
             },
@@ -64,20 +441,175 @@ impl FileManager {
 
     pub fn consume_response(&mut self, response: LightWorkerResponse){
         match response {
-            LightWorkerResponse::Loaded(files, path) => {
-                self.path = path;
-                self.files = files;
+            LightWorkerResponse::Loading{path, files, first} => {
+                match first {
+                    true => self.enter(path, files, HashMap::new()),
+                    false if self.loading => {
+                        self.listing.extend(files.iter().cloned());
+                        self.files.extend(files);
+                    },
+                    false => {},
+                }
+                self.loading = true;
+            },
+            LightWorkerResponse::Loaded(Listing { path, files, parent, subtrees, streamed, refresh, from_cache, cache_stats }) => {
+                self.cache_stats = cache_stats;
+                match streamed && self.loading {
+                    true => {
+                        self.listing.extend(files);
+                        self.subtrees = subtrees;
+                        self.flatten();
+                    },
+                    false => self.enter(path.clone(), files, subtrees),
+                }
+                self.loading = false;
+                // the marks are kept on a reload of the same folder for the files that still exist
+                self.marked.retain(|name| self.files.iter().any(|file| file.name() == name));
+                if self.show_parent {
+                    self.cache_parent_listing(path.clone(), self.listing.clone());
+                }
+                if let (Some(parent_path), Some(parent)) = (path.parent(), parent) {
+                    self.cache_parent_listing(parent_path.to_path_buf(), parent);
+                }
+                if !refresh {
+                    self.increment_light_sync_id();
+                }
+                // the cached listing is shown at once and revalidated by a refresh from the disk
+                if from_cache {
+                    self.refreshing = true;
+                    let _ = self.send_load(path.clone(), None, true);
+                }
+                self.watch();
+                if self.auto_measure {
+                    let _ = self.measure_listing();
+                }
+            },
+            LightWorkerResponse::Expanded(files, path) => {
+                // the subfolder is only shown if its row is still visible in the current folder
+                if let Ok(folder) = path.strip_prefix(&self.path)
+                    && self.files.iter().any(|file| Path::new(file.name()) == folder) {
+                    self.subtrees.insert(folder.to_path_buf(), files);
+                    self.flatten();
+                }
                 self.increment_light_sync_id();
             },
-            LightWorkerResponse::Read(content, mut path) => {
-                path.pop();
-                if self.path == path {
-                    self.selected_file_preview_buffer = content;
+            LightWorkerResponse::Read(mut preview, path) => {
+                if self.is_selected_path(&path) {
+                    // a file read again keeps its scroll, a followed file shows its last lines
+                    let previous = (self.rereading.as_ref() == Some(&path)).then_some(&self.selected_file_preview);
+                    preview.resume(previous, self.follow, self.preview_area.1 as usize);
+                    self.selected_file_preview = preview;
                 }
+                self.rereading = None;
                 self.increment_light_sync_id();
             },
         }
     }
+
+    /// Heavy worker responses are not in lockstep with the file manager, they are only kept if they match the selected file
+    pub fn consume_heavy_response(&mut self, response: HeavyWorkerResponse) {
+        match response {
+            HeavyWorkerResponse::Listed(preview, path) => {
+                if self.is_selected_path(&path) {
+                    self.selected_file_preview = preview;
+                }
+            },
+            HeavyWorkerResponse::Indexed(source) => {
+                if let Some(path) = self.indexing.take_if(|path| archive::split_virtual_path(path).is_some_and(|(archive, _)| source.reads(&archive))) {
+                    self.status = None;
+                    let _ = self.send_load(path, Some(source), false);
+                }
+            },
+            HeavyWorkerResponse::JobProgress{id, done, total} => {
+                if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                    job.done = done;
+                    job.total = total;
+                }
+            },
+            HeavyWorkerResponse::JobFinished(id, summary) => {
+                self.status = Some(summary);
+                self.finish_job(id);
+            },
+            HeavyWorkerResponse::Walked{id, files} => {
+                if let Some(walk) = self.walk.as_ref().filter(|walk| walk.id == id) {
+                    self.files.extend(files);
+                    self.status = Some(format!("Listing recursively (depth {}): {} files...", walk.depth, self.files.len()));
+                }
+            },
+            HeavyWorkerResponse::Measured{id: _, path, usage} => {
+                if self.is_selected_path(&path) {
+                    self.status = Some(format!("{}: {} apparent, {} on disk, {} files", path.file_name().unwrap_or_default().to_string_lossy(),
+                        Size::from(usage.apparent), Size::from(usage.disk), usage.files));
+                }
+                self.sizes.insert(path, FolderSize::Measured(usage));
+            },
+            HeavyWorkerResponse::MeasureFinished(id) => {
+                // the folders left by a cancelled measure are not measuring anymore
+                self.measures.retain(|measure| measure.id != id);
+                self.sizes.retain(|_, size| !matches!(size, FolderSize::Measuring(measuring) if *measuring == id));
+            },
+            HeavyWorkerResponse::WalkFinished(id, found) => {
+                if let Some(walk) = self.walk.as_ref().filter(|walk| walk.id == id) {
+                    self.status = Some(format!("{} files under {} (depth {})", found, self.path.display(), walk.depth));
+                }
+            },
+        }
+    }
+
+    /// Removes the failed job and displays its error
+    pub fn consume_job_error(&mut self, id: usize, message: String) {
+        if let Some(job) = self.jobs.iter().find(|job| job.id == id) {
+            self.status = Some(format!("{} failed: {}", job.description, message));
+        }
+        self.finish_job(id);
+    }
+
+    /// Reloads the folder or reads the previewed file again after a change on the disk, a recursive listing is not refreshed as it
+    /// would start over and a folder still coming by batches is already listed from the disk
+    pub fn consume_watcher_response(&mut self, response: WatcherResponse) {
+        let WatcherResponse::Changed(path) = response;
+        if path == self.path && self.walk.is_none() && !self.loading {
+            self.forget_sizes(&path, false);
+            self.refreshing = true;
+            let _ = self.send_load(path, None, true);
+        }
+        else if self.is_selected_path(&path) {
+            self.rereading = Some(path);
+            let _ = self.read_content(self.selected);
+        }
+    }
+
+    /// Displays the error of the recursive listing, the files already found stay listed
+    pub fn consume_walk_error(&mut self, id: usize, message: String) {
+        if self.walk.as_ref().is_some_and(|walk| walk.id == id) {
+            self.status = Some(format!("Recursive listing failed: {}", message));
+        }
+    }
+
+    /// Marks the size of the folder as unknown, the error is displayed if the folder is selected
+    pub fn consume_measure_error(&mut self, path: PathBuf, message: String) {
+        if self.is_selected_path(&path) {
+            self.status = Some(message);
+        }
+        self.sizes.insert(path, FolderSize::Failed);
+    }
+
+    /// Gives up the load of the folder waiting for the index of the archive
+    pub fn consume_index_error(&mut self, path: &Path, message: String) {
+        if self.indexing.as_ref().and_then(|folder| archive::split_virtual_path(folder)).is_some_and(|(archive, _)| archive == path) {
+            self.indexing = None;
+            self.pending_selection = None;
+            self.pending_history = None;
+            self.status = Some(format!("Error indexing archive: {}", message));
+        }
+    }
+
+    /// Displays the error of a heavy worker preview if it matches the selected file
+    pub fn consume_heavy_error(&mut self, path: &Path, message: String) {
+        if self.is_selected_path(path) {
+            self.selected_file_preview = Preview::Message(message);
+        }
+    }
 }
 
 // private action methods
@@ -87,12 +619,124 @@ impl FileManager {
             Some(parent) => parent.to_path_buf(),
             None => PathBuf::from("/")
         };
-        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action: LightWorkerAction::Load(parent)})?;
+        self.load(parent)?;
+        // the folder we come from is selected in the parent
+        self.pending_selection = self.name_in_parent();
         Ok(())
     }
 
     fn open_folder(&self, path: PathBuf) -> Result<(), FileManagerError> {
-        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action: LightWorkerAction::Load(path)})?;
+        self.load(path)
+    }
+
+    /// Sends the load of the folder, its parent is listed along when the miller columns need it and it's not cached,
+    /// a reload lists the expanded subfolders again
+    fn load(&self, path: PathBuf) -> Result<(), FileManagerError> {
+        self.send_load(path, None, false)
+    }
+
+    /// Sends the load of the folder with the index of its archive when the heavy worker has just built it,
+    /// a refresh after a change on the disk is not counted in the lockstep with the worker
+    fn send_load(&self, path: PathBuf, archive: Option<ArchiveSource>, refresh: bool) -> Result<(), FileManagerError> {
+        let with_parent = self.show_parent && path.parent().is_some_and(|parent| !self.parent_listings.contains_key(parent));
+        let expanded = match path == self.path {
+            true => self.subtrees.keys().cloned().collect(),
+            false => Vec::new(),
+        };
+        // a reload lists the folder from the disk, entering a folder uses its cached listing if it didn't change
+        let cached = path != self.path;
+        let request = LoadRequest { path, options: self.listing_options, with_parent, expanded, cached, archive, refresh };
+        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action: LightWorkerAction::Load(request)})?;
+        Ok(())
+    }
+
+    /// Replaces the listing by the files under the folder up to the depth, they are added as the heavy worker finds them.
+    /// Without depth the folder listing is loaded back
+    fn list_recursively(&mut self, depth: Option<usize>) -> Result<(), FileManagerError> {
+        if let Some(walk) = self.walk.take() {
+            walk.cancel.store(true, Ordering::Relaxed);
+            self.status = None;
+        }
+        let depth = match depth {
+            Some(depth) => depth.clamp(1, MAX_WALK_DEPTH),
+            None => return self.reload_files(),
+        };
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let job = WalkJob { path: self.path.clone(), depth, options: self.listing_options };
+        self.heavy_worker_channel.send(HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::Walk{id, job, cancel: cancel.clone()}))?;
+        self.walk = Some(Walk { id, depth, cancel });
+        self.listing.clear();
+        self.subtrees.clear();
+        self.files.clear();
+        self.guides.clear();
+        self.marked.clear();
+        self.selected = None;
+        self.selected_file_preview = Preview::Empty;
+        self.status = Some(format!("Listing recursively (depth {})...", depth));
+        Ok(())
+    }
+
+    /// Lists the folder at the index to show its content under its row, links are not followed to avoid cycles
+    fn expand(&self, index: usize) -> Result<(), FileManagerError> {
+        let file = match self.files.get(index) {
+            Some(file) if file.is_folder() && !self.subtrees.contains_key(Path::new(file.name())) => file,
+            _ => return Ok(()),
+        };
+        let action = LightWorkerAction::Expand(self.path.join(file.name()), self.listing_options);
+        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action})?;
+        Ok(())
+    }
+
+    /// Collapses the expanded folder at the index, or the folder containing the row otherwise,
+    /// the folder is selected if the selected file was inside it
+    fn collapse(&mut self, index: usize) {
+        // the recursive listing has no folder rows
+        if self.walk.is_some() {
+            return;
+        }
+        let row = match self.files.get(index) {
+            Some(file) => PathBuf::from(file.name()),
+            None => return,
+        };
+        let folder = match row.parent() {
+            _ if self.subtrees.contains_key(&row) => row,
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => return,
+        };
+        self.subtrees.retain(|subtree, _| !subtree.starts_with(&folder));
+        self.marked.retain(|name| Path::new(name) == folder || !Path::new(name).starts_with(&folder));
+        let hidden = self.selected_file().is_some_and(|file| Path::new(file.name()) != folder && Path::new(file.name()).starts_with(&folder));
+        self.flatten();
+        if hidden {
+            self.selected = self.files.iter().position(|file| Path::new(file.name()) == folder);
+        }
+    }
+
+    /// Rebuilds the rows from the folder listing and the expanded subfolders, the selected file keeps its selection
+    fn flatten(&mut self) {
+        let selected = self.selected_file().map(|file| file.name().to_string());
+        let (mut files, mut guides) = (Vec::new(), Vec::new());
+        flatten_folder(&self.listing, Path::new(""), "", &self.subtrees, &mut files, &mut guides);
+        self.files = files;
+        self.guides = guides;
+        self.selected = selected.and_then(|name| self.files.iter().position(|file| file.name() == name));
+    }
+
+    /// Loads a folder given by its full path, or the parent of a file with the file selected,
+    /// a missing folder is reported instead of being sent to the worker
+    fn open_path(&mut self, path: PathBuf) -> Result<(), FileManagerError> {
+        if archive::folder_exists(&path) {
+            return self.open_folder(path);
+        }
+        match (path.is_file(), path.parent(), path.file_name()) {
+            (true, Some(parent), Some(name)) => {
+                self.open_folder(parent.to_path_buf())?;
+                self.pending_selection = Some(name.to_string_lossy().to_string());
+            },
+            _ => self.status = Some(format!("Folder not found: {}", path.display())),
+        }
         Ok(())
     }
 
@@ -103,11 +747,12 @@ impl FileManager {
 
     fn open_link(&self, file: &File) -> Result<(), FileManagerError> {
         match file.file_type() {
-            FileType::Link { target, is_dead } => {
+            FileType::Link { is_dead, .. } => {
                 if *is_dead {
                     return Err(FileManagerError::OpenFileFailed("Dead Symbolic Link".to_string()));
                 }
-                let path = fs::canonicalize(target).map_err(|_e| FileManagerError::OpenFileFailed("Failed to resolve the link".to_string()))?;
+                // the link is resolved from its own path as its target may be relative to the link parent
+                let path = fs::canonicalize(self.path.join(file.name())).map_err(|_e| FileManagerError::OpenFileFailed("Failed to resolve the link".to_string()))?;
 
                 let metadata = fs::metadata(&path).map_err(|_e| FileManagerError::OpenFileFailed("Failed to resolve targeted file".to_string()))?;
 
@@ -125,17 +770,100 @@ impl FileManager {
     fn open(&self, index: usize) -> Result<(), FileManagerError> {
         match self.files()[index].file_type() {
             FileType::Folder => self.open_folder(self.path.join(self.files[index].name())),
-            FileType::File => self.open_file(self.path.join(self.files[index].name())),
+            FileType::File => {
+                let path = self.path.join(self.files[index].name());
+                if archive::split_virtual_path(&self.path).is_some() {
+                    return Err(FileManagerError::OpenFileFailed("Files inside an archive can't be opened".to_string()));
+                }
+                // archives are browsed like folders through their virtual root
+                match ArchiveKind::detect(&path) {
+                    Some(_) => self.open_folder(archive::virtual_root(&path)),
+                    None => self.open_file(path),
+                }
+            },
             FileType::Link { .. } => self.open_link(&self.files[index]),
-            FileType::Unknown => return Err(FileManagerError::OpenUnknownFileType("Unknown file type".to_string())),
+            FileType::Unknown => Err(FileManagerError::OpenUnknownFileType("Unknown file type".to_string())),
+        }
+    }
+
+    fn jump_to_link_target(&mut self, index: usize) -> Result<(), FileManagerError> {
+        let file = match self.files.get(index) {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        match file.file_type() {
+            FileType::Link { is_dead, .. } => {
+                if *is_dead {
+                    return Err(FileManagerError::OpenFileFailed("Dead Symbolic Link".to_string()));
+                }
+                let target = fs::canonicalize(self.path.join(file.name())).map_err(|_e| FileManagerError::OpenFileFailed("Failed to resolve the link".to_string()))?;
+                // the folder containing the target is loaded with the target selected, the root has no parent so it's loaded as is
+                let (folder, name) = match (target.parent(), target.file_name()) {
+                    (Some(parent), Some(name)) => (parent.to_path_buf(), Some(name.to_string_lossy().to_string())),
+                    _ => (target, None),
+                };
+                self.pending_selection = name;
+                self.load(folder)
+            },
+            _ => Err(FileManagerError::OpenUnknownFileType("Not a symbolic link".to_string())),
         }
     }
 
-    fn reload_files(&self) -> Result<(), FileManagerError> {
-        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action: LightWorkerAction::Load(self.path.clone())})?;
+    /// Loads the folder of the history entry with the file it was left on selected
+    fn go_to_history(&mut self, index: Option<usize>) -> Result<(), FileManagerError> {
+        let (index, entry) = match index.and_then(|index| Some((index, self.history.get(index)?))) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+        self.load(entry.path.clone())?;
+        self.pending_selection = entry.selected.clone();
+        self.pending_history = Some(index);
         Ok(())
     }
 
+    /// Records the newly loaded folder in the history, a back or forward load moves in the history
+    /// while any other new folder drops the forward entries
+    fn record_history(&mut self, path: &Path) {
+        let selected = self.selected_file().map(|file| file.name().to_string());
+        if let Some(entry) = self.history.get_mut(self.history_index)
+            && entry.path == self.path {
+            entry.selected = selected;
+        }
+        match self.pending_history.take() {
+            Some(index) if self.history.get(index).is_some_and(|entry| entry.path == path) => self.history_index = index,
+            // a reload of the current folder
+            _ if self.history.get(self.history_index).is_some_and(|entry| entry.path == path) => {},
+            _ => {
+                self.history.truncate(self.history_index + 1);
+                self.history.push(HistoryEntry { path: path.to_path_buf(), selected: None });
+                if self.history.len() > MAX_HISTORY {
+                    self.history.remove(0);
+                }
+                self.history_index = self.history.len() - 1;
+            },
+        }
+    }
+
+    /// Loads the current folder again, its parent listing is refreshed too, a recursive listing is started again
+    fn reload_files(&mut self) -> Result<(), FileManagerError> {
+        if let Some(depth) = self.walk_depth() {
+            return self.list_recursively(Some(depth));
+        }
+        if let Some(parent) = self.path.parent() {
+            self.parent_listings.remove(parent);
+        }
+        self.forget_sizes(&self.path.clone(), true);
+        self.load(self.path.clone())
+    }
+
+    /// Returns the name of the current folder in its parent, the root of an archive is the archive file
+    fn name_in_parent(&self) -> Option<String> {
+        match archive::split_virtual_path(&self.path) {
+            Some((archive, inner)) if inner.as_os_str().is_empty() => archive.file_name().map(|name| name.to_string_lossy().to_string()),
+            _ => self.path.file_name().map(|name| name.to_string_lossy().to_string()),
+        }
+    }
+
     fn read_content(&mut self, index: Option<usize>) -> Result<(), FileManagerError> {
         let index = match index {
             Some(index) => index,
@@ -144,26 +872,351 @@ impl FileManager {
         if index >= self.files().len() {
             return Ok(());
         }
-        if !matches!(self.files()[index].file_type(),FileType::File) {
-            self.selected_file_preview_buffer = String::from("No preview available");
-            return Ok(());
+        // moving the selection while a big folder loads drops the file asked for by the navigation
+        if self.loading && self.selected.is_some_and(|selected| selected != index) {
+            self.pending_selection = None;
         }
+        self.selected = Some(index);
+        self.watch();
         let path = self.path.join(self.files()[index].name());
-        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action: LightWorkerAction::Read(path)})?;
+        let action = match self.files()[index].file_type() {
+            // archives are listed by the heavy worker so a big one doesn't delay the folder loads
+            FileType::File if ArchiveKind::detect(&path).is_some() && archive::split_virtual_path(&path).is_none() => {
+                self.selected_file_preview = Preview::Message(String::from("Listing archive..."));
+                self.heavy_worker_channel.send(HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::ListArchive(path)))?;
+                return Ok(());
+            },
+            FileType::File => {
+                // the forced encoding only applies to the file it was chosen for
+                let encoding = self.forced_encoding.as_ref().filter(|(forced_path, _)| *forced_path == path).map(|(_, encoding)| *encoding);
+                LightWorkerAction::Read(path, ReadOptions { encoding, area: self.preview_area, tail: self.follow })
+            },
+            FileType::Folder => LightWorkerAction::List(path, self.listing_options),
+            FileType::Link { .. } => LightWorkerAction::ResolveLink(path),
+            _ => {
+                self.selected_file_preview = Preview::Message(String::from("No preview available"));
+                return Ok(());
+            },
+        };
+        self.light_worker_channel.send(LightWorkerMessage::WorkerAction{sync_id: self.light_sync_id, action})?;
+        Ok(())
+    }
+
+    /// Re-reads the selected file with the given encoding, None goes back to the automatic detection
+    fn force_encoding(&mut self, encoding: Option<&'static Encoding>) -> Result<(), FileManagerError> {
+        let file = match self.selected_file() {
+            Some(file) if file.is_file() => file,
+            _ => return Ok(()),
+        };
+        let path = self.path.join(file.name());
+        self.forced_encoding = encoding.map(|encoding| (path, encoding));
+        self.read_content(self.selected)
+    }
+
+    /// Extracts the archive into the current folder or into a subfolder of it when a folder name is given
+    fn extract(&mut self, index: usize, folder: String, policy: ConflictPolicy) -> Result<(), FileManagerError> {
+        let file = match self.files.get(index) {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let path = self.path.join(file.name());
+        if ArchiveKind::detect(&path).is_none() || archive::split_virtual_path(&path).is_some() {
+            return Err(FileManagerError::OpenFileFailed("Not an archive of the local filesystem".to_string()));
+        }
+        let description = format!("Extracting {}", file.base_name());
+        // an archive of an expanded subfolder is extracted next to it
+        let destination = path.parent().unwrap_or(&self.path).join(folder.trim());
+        self.start_job(description, destination.clone(), |id, cancel| HeavyWorkerAction::Extract{id, job: ExtractJob { archive: path, destination, policy }, cancel})
+    }
+
+    /// Compresses the marked files, or the file at the index if none is marked, into an archive of the current folder
+    fn compress(&mut self, index: Option<usize>, name: String, kind: ArchiveKind, policy: ConflictPolicy) -> Result<(), FileManagerError> {
+        if archive::split_virtual_path(&self.path).is_some() {
+            return Err(FileManagerError::OpenFileFailed("Files inside an archive can't be compressed".to_string()));
+        }
+        let sources = self.sources(index);
+        if sources.is_empty() || name.trim().is_empty() {
+            return Ok(());
+        }
+        let file_name = format!("{}{}", name.trim(), kind.extension());
+        let description = format!("Compressing {}", file_name);
+        let destination = self.path.join(file_name);
+        self.marked.clear();
+        self.start_job(description, destination.clone(), |id, cancel| HeavyWorkerAction::Compress{id, job: CompressJob { sources, destination, kind, policy }, cancel})
+    }
+
+    /// Copies or moves the marked files, or the file at the index if none is marked, into the destination folder
+    fn transfer(&mut self, index: Option<usize>, destination: String, mode: TransferMode, policy: ConflictPolicy) -> Result<(), FileManagerError> {
+        let sources = self.sources(index);
+        if sources.is_empty() {
+            return Ok(());
+        }
+        let destination = match expand(destination.trim(), &self.path) {
+            Ok(destination) => destination,
+            Err(error) => {
+                self.status = Some(error);
+                return Ok(());
+            },
+        };
+        let description = match mode {
+            TransferMode::Copy => format!("Copying {} items", sources.len()),
+            TransferMode::Move => format!("Moving {} items", sources.len()),
+        };
+        self.marked.clear();
+        self.start_job(description, destination.clone(), |id, cancel| HeavyWorkerAction::Transfer{id, job: TransferJob { sources, destination, mode, policy }, cancel})
+    }
+
+    /// Returns the paths of the marked files, or of the file at the index if none is marked
+    fn sources(&self, index: Option<usize>) -> Vec<PathBuf> {
+        match self.marked.is_empty() {
+            true => index.and_then(|index| self.files.get(index)).map(|file| vec![self.path.join(file.name())]).unwrap_or_default(),
+            false => self.files.iter().filter(|file| self.marked.contains(file.name())).map(|file| self.path.join(file.name())).collect(),
+        }
+    }
+
+    /// Measures the total size of the folders on the heavy worker, their sizes are shown as they complete
+    fn measure(&mut self, folders: Vec<PathBuf>) -> Result<(), FileManagerError> {
+        if folders.is_empty() {
+            return Ok(());
+        }
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        for folder in &folders {
+            self.sizes.insert(folder.clone(), FolderSize::Measuring(id));
+        }
+        let job = MeasureJob { paths: folders, cross_filesystems: self.cross_filesystems };
+        self.heavy_worker_channel.send(HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::Measure{id, job, cancel: cancel.clone()}))?;
+        self.measures.push(Measure { id, cancel });
+        Ok(())
+    }
+
+    /// Forgets the measured sizes of the folder and of the folders containing it as their totals changed with it,
+    /// with `contents` the sizes of the folders under it are forgotten too. The sizes being measured are kept
+    fn forget_sizes(&mut self, path: &Path, contents: bool) {
+        self.sizes.retain(|folder, size| matches!(size, FolderSize::Measuring(_)) || !(path.starts_with(folder) || contents && folder.starts_with(path)));
+    }
+
+    /// Measures the folders of the listing whose size is not known yet, the folders inside an archive are left out
+    fn measure_listing(&mut self) -> Result<(), FileManagerError> {
+        if archive::split_virtual_path(&self.path).is_some() {
+            return Ok(());
+        }
+        let folders = self.files.iter()
+            .filter(|file| file.is_folder())
+            .map(|file| self.path.join(file.name()))
+            .filter(|path| !self.sizes.contains_key(path))
+            .collect();
+        self.measure(folders)
+    }
+
+    fn start_job(&mut self, description: String, destination: PathBuf, action: impl FnOnce(usize, Arc<AtomicBool>) -> HeavyWorkerAction) -> Result<(), FileManagerError> {
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.heavy_worker_channel.send(HeavyWorkerMessage::WorkerAction(action(id, cancel.clone())))?;
+        self.jobs.push(Job { id, description, folder: self.path.clone(), destination, done: 0, total: 0, cancel });
         Ok(())
     }
 
+    /// Removes the job and reloads its folder if it's still displayed to show the files it created,
+    /// the measured sizes of the folders it changed are forgotten
+    fn finish_job(&mut self, id: usize) {
+        let position = match self.jobs.iter().position(|job| job.id == id) {
+            Some(position) => position,
+            None => return,
+        };
+        let job = self.jobs.remove(position);
+        self.forget_sizes(&job.folder, false);
+        self.forget_sizes(&job.destination, true);
+        if job.folder == self.path {
+            let _ = self.reload_files();
+        }
+    }
+
+    /// Shows the files of the loaded folder, its later batches are appended to them
+    fn enter(&mut self, path: PathBuf, files: Vec<File>, subtrees: HashMap<PathBuf, Vec<File>>) {
+        // a reload is not a visit, the first load of the starting folder is
+        if self.path != path || self.history.is_empty() {
+            // a failure to save the visit only loses it, the navigation is not affected
+            let _ = self.frecency.borrow_mut().record(&path);
+        }
+        self.record_history(&path);
+        let selected = self.selected_file().map(|file| file.name().to_string());
+        if let Some(name) = &selected {
+            self.cursors.insert(self.path.clone(), (name.clone(), self.selected.unwrap_or(0)));
+        }
+        self.refreshed = std::mem::take(&mut self.refreshing) && self.path == path;
+        // any load leaves the recursive listing
+        if let Some(walk) = self.walk.take() {
+            walk.cancel.store(true, Ordering::Relaxed);
+        }
+        self.listing = files;
+        self.subtrees = subtrees;
+        self.flatten();
+        if self.path != path {
+            self.marked.clear();
+            // the sizes still measured are only shown in the folder left
+            self.measures.drain(..).for_each(|measure| measure.cancel.store(true, Ordering::Relaxed));
+            self.sizes.retain(|_, size| !matches!(size, FolderSize::Measuring(_)));
+        }
+        self.path = path;
+        // a refresh keeps the selected file, flattening the rows kept its index. In a big folder the file may come in a later batch,
+        // it's then selected once its batch comes in like the file asked for by a navigation
+        if !self.refreshed {
+            self.selected = None;
+        }
+        else if self.selected.is_none() && self.pending_selection.is_none() {
+            self.pending_selection = selected;
+        }
+    }
+
+    /// Keeps the listing for the miller columns, each loaded folder is the parent of the folders it contains
+    fn cache_parent_listing(&mut self, path: PathBuf, files: Vec<File>) {
+        if !self.show_parent {
+            return;
+        }
+        if self.parent_listings.len() >= MAX_PARENT_LISTINGS && !self.parent_listings.contains_key(&path) {
+            self.parent_listings.clear();
+        }
+        self.parent_listings.insert(path, files);
+    }
+
+    /// Watches the current folder and the selected file, the archives are not watched
+    fn watch(&self) {
+        let local = |path: PathBuf| archive::split_virtual_path(&path).is_none().then_some(path);
+        let folder = local(self.path.clone());
+        let file = self.selected_file().and_then(|file| local(self.path.join(file.name())));
+        if let Some(channel) = &self.watcher_channel {
+            let _ = channel.send(WatcherMessage::Watch{folder, file});
+        }
+    }
+
+    fn is_selected_path(&self, path: &Path) -> bool {
+        self.selected_file().is_some_and(|file| self.path.join(file.name()) == path)
+    }
+
     fn _create_folder(&mut self, path: PathBuf) -> std::io::Result<()> {
         fs::create_dir_all(&path)?;
         Ok(())
     }
 }
 
+/// Adds the rows of the folder listing and of its expanded subfolders, each row is named by its path relative to the listed folder.
+/// The rows of the listed folder have no guide, the deeper ones are drawn under their folder
+fn flatten_folder(listing: &[File], folder: &Path, indent: &str, subtrees: &HashMap<PathBuf, Vec<File>>, files: &mut Vec<File>, guides: &mut Vec<String>) {
+    let root = folder.as_os_str().is_empty();
+    for (index, file) in listing.iter().enumerate() {
+        let last = index + 1 == listing.len();
+        let path = folder.join(file.name());
+        guides.push(match (root, last) {
+            (true, _) => String::new(),
+            (false, true) => format!("{}└─ ", indent),
+            (false, false) => format!("{}├─ ", indent),
+        });
+        files.push(file.clone().with_name(path.to_string_lossy().to_string()));
+        if let Some(children) = subtrees.get(&path) {
+            let indent = match (root, last) {
+                (true, _) => String::new(),
+                (false, true) => format!("{}   ", indent),
+                (false, false) => format!("{}│  ", indent),
+            };
+            flatten_folder(children, &path, &indent, subtrees, files, guides);
+        }
+    }
+}
+
+/// Visited folder with the file selected when it was left
+#[derive(Clone)]
+struct HistoryEntry {
+    path: PathBuf,
+    selected: Option<String>,
+}
+
+/// Recursive listing running or done on the heavy worker
+struct Walk {
+    id: usize,
+    depth: usize,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Folder sizes being measured by the heavy worker
+struct Measure {
+    id: usize,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Total size of a folder in the size column
+enum FolderSize {
+    // measured by the measure with this id
+    Measuring(usize),
+    Measured(DiskUsage),
+    Failed,
+}
+
+/// Long operation running in the background on the heavy worker
+pub struct Job {
+    id: usize,
+    description: String,
+    // folder the job was started from, reloaded when the job ends
+    folder: PathBuf,
+    // file or folder written by the job, the measured sizes containing it are forgotten when the job ends
+    destination: PathBuf,
+    done: u64,
+    total: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn percent(&self) -> u64 {
+        (self.done * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
 pub enum FileManagerAction {
     Open(usize),
     GoToParent,
+    GoBack,
+    GoForward,
     Reload,
     ReadContent(Option<usize>),
+    JumpToLinkTarget(usize),
+    ForceEncoding(Option<&'static Encoding>),
+    ToggleHidden,
+    ToggleMark(usize),
+    ToggleFollow,
+    // measures the total size of the marked folders, or of the folder at the index if none is marked
+    MeasureFolders(Option<usize>),
+    // measures the folders of each listing once loaded
+    ToggleAutoMeasure,
+    // lets the measures count the folders mounted under the measured ones
+    ToggleCrossFilesystems,
+    // switches the folder sizes of the size column between their apparent size and their usage on the disk
+    ToggleDiskUsage,
+    // shows the usage of the listing cache of the light worker in the status bar
+    ShowCacheStats,
+    // lists the files under the folder up to the depth, None goes back to the folder listing
+    ListRecursively(Option<usize>),
+    // shows the content of the folder at the index under its row
+    Expand(usize),
+    // hides the content of the folder at the index, or of the folder containing the row
+    Collapse(usize),
+    Extract{index: usize, folder: String, policy: ConflictPolicy},
+    Compress{index: Option<usize>, name: String, kind: ArchiveKind, policy: ConflictPolicy},
+    // the destination is a typed path, relative to the current folder
+    Transfer{index: Option<usize>, destination: String, mode: TransferMode, policy: ConflictPolicy},
+    CancelJobs,
+    OpenPath(PathBuf),
+    // lists the parent folder along the current one for the miller columns
+    ShowParent(bool),
+    SetBookmark(char),
+    JumpToBookmark(char),
+    DeleteBookmark(usize),
+    ImportGtkBookmarks,
     CreateFolder(String),
 }
 
@@ -182,3 +1235,193 @@ impl From<SendError<LightWorkerMessage>> for FileManagerError {
         FileManagerError::SendMessageFailed(error.to_string())
     }
 }
+
+impl From<SendError<HeavyWorkerMessage>> for FileManagerError {
+    fn from(error: SendError<HeavyWorkerMessage>) -> Self {
+        FileManagerError::SendMessageFailed(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::time::SystemTime;
+    use crate::file::{File, FileType};
+    use crate::disk_usage::DiskUsage;
+    use crate::workers::{LightWorkerMessage, LightWorkerAction, LightWorkerResponse, Listing, CacheStats};
+    use super::{FileManager, FileManagerAction, FolderSize, MAX_HISTORY, flatten_folder};
+
+    fn file(name: &str, file_type: FileType) -> File {
+        File::new(name.to_string(), file_type, SystemTime::UNIX_EPOCH, 0)
+    }
+
+    /// File manager without workers, the tests only look at its state
+    fn file_manager(path: &str) -> FileManager {
+        FileManager::new(Path::new(path), 0, mpsc::channel().0, mpsc::channel().0)
+    }
+
+    fn visit(file_manager: &mut FileManager, path: &str) {
+        file_manager.record_history(Path::new(path));
+        file_manager.path = PathBuf::from(path);
+    }
+
+    fn history(file_manager: &FileManager) -> Vec<&str> {
+        file_manager.history.iter().map(|entry| entry.path.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_record_history() {
+        let mut file_manager = file_manager("/a");
+        for path in ["/a", "/b", "/c"] {
+            visit(&mut file_manager, path);
+        }
+        assert_eq!((history(&file_manager), file_manager.history_index), (vec!["/a", "/b", "/c"], 2));
+        // going back moves in the history, a reload doesn't change it
+        file_manager.pending_history = Some(1);
+        visit(&mut file_manager, "/b");
+        visit(&mut file_manager, "/b");
+        assert_eq!((history(&file_manager), file_manager.history_index), (vec!["/a", "/b", "/c"], 1));
+        // a new folder drops the forward entries, the file selected in the folder left is kept in its entry
+        file_manager.files = vec![file("x", FileType::File), file("y", FileType::File)];
+        file_manager.selected = Some(1);
+        visit(&mut file_manager, "/d");
+        assert_eq!((history(&file_manager), file_manager.history_index), (vec!["/a", "/b", "/d"], 2));
+        assert_eq!(file_manager.history[1].selected.as_deref(), Some("y"));
+        // a back entry that doesn't match the loaded folder is a new visit
+        file_manager.pending_history = Some(0);
+        visit(&mut file_manager, "/e");
+        assert_eq!((history(&file_manager), file_manager.history_index), (vec!["/a", "/b", "/d", "/e"], 3));
+
+        for index in 0..MAX_HISTORY {
+            visit(&mut file_manager, &format!("/f{}", index));
+        }
+        assert_eq!(file_manager.history.len(), MAX_HISTORY);
+        assert_eq!(file_manager.history_index, MAX_HISTORY - 1);
+        assert_eq!(history(&file_manager)[0], "/f0");
+    }
+
+    #[test]
+    fn test_take_selection_after_load() {
+        let mut file_manager = file_manager("/a");
+        assert_eq!(file_manager.take_selection_after_load(), None);
+        file_manager.files = vec![file("a", FileType::File), file("b", FileType::File), file("c", FileType::File)];
+        assert_eq!(file_manager.take_selection_after_load(), Some(0));
+        // the file asked for comes before the cursor of the folder
+        file_manager.cursors.insert(PathBuf::from("/a"), ("b".to_string(), 1));
+        file_manager.pending_selection = Some("c".to_string());
+        assert_eq!(file_manager.take_selection_after_load(), Some(2));
+        assert_eq!(file_manager.pending_selection, None);
+        assert_eq!(file_manager.take_selection_after_load(), Some(1));
+        // a file asked for that is gone is forgotten, the cursor follows its file and falls back to its index once the file is gone
+        file_manager.files.remove(1);
+        file_manager.pending_selection = Some("z".to_string());
+        assert_eq!(file_manager.take_selection_after_load(), Some(1));
+        assert_eq!(file_manager.pending_selection, None);
+        file_manager.cursors.insert(PathBuf::from("/a"), ("x".to_string(), 5));
+        assert_eq!(file_manager.take_selection_after_load(), Some(1));
+        file_manager.cursors.insert(PathBuf::from("/a"), ("c".to_string(), 5));
+        assert_eq!(file_manager.take_selection_after_load(), Some(1));
+    }
+
+    #[test]
+    fn test_pending_selection_while_loading() {
+        let mut file_manager = file_manager("/a");
+        file_manager.loading = true;
+        file_manager.pending_selection = Some("z".to_string());
+        file_manager.files = vec![file("a", FileType::File)];
+        // the file asked for may come in a later batch
+        assert_eq!(file_manager.take_selection_after_load(), Some(0));
+        assert_eq!(file_manager.take_pending_selection(), None);
+        assert_eq!(file_manager.pending_selection.as_deref(), Some("z"));
+        file_manager.files.push(file("z", FileType::File));
+        assert_eq!(file_manager.take_pending_selection(), Some(1));
+        assert_eq!(file_manager.pending_selection, None);
+        // it's given up once the folder is loaded without it
+        file_manager.pending_selection = Some("y".to_string());
+        file_manager.loading = false;
+        assert_eq!(file_manager.take_pending_selection(), None);
+        assert_eq!(file_manager.pending_selection, None);
+    }
+
+    fn folders(names: &[&str]) -> Vec<File> {
+        names.iter().map(|name| file(name, FileType::Folder)).collect()
+    }
+
+    fn loaded(path: &str, files: &[&str], parent: Option<&[&str]>) -> LightWorkerResponse {
+        let (path, files, parent) = (PathBuf::from(path), folders(files), parent.map(folders));
+        LightWorkerResponse::Loaded(Listing { path, files, parent, subtrees: HashMap::new(), streamed: false, refresh: false, from_cache: false, cache_stats: CacheStats::default() })
+    }
+
+    /// Returns the folders of the loads sent to the light worker, and if their parent is listed along
+    fn loads(receiver: &mpsc::Receiver<LightWorkerMessage>) -> Vec<(String, bool)> {
+        receiver.try_iter().filter_map(|message| match message {
+            LightWorkerMessage::WorkerAction{action: LightWorkerAction::Load(request), ..} => Some((request.path.to_string_lossy().to_string(), request.with_parent)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_parent_listing() {
+        let (sender, receiver) = mpsc::channel();
+        let mut file_manager = FileManager::new(Path::new("/a/b"), 0, sender, mpsc::channel().0);
+        file_manager.dispatch(FileManagerAction::ShowParent(true)).unwrap();
+        assert_eq!(loads(&receiver), vec![("/a/b".to_string(), true)]);
+        file_manager.consume_response(loaded("/a/b", &["c"], Some(&["x", "b", "z"])));
+        let (files, cursor) = file_manager.parent_listing().unwrap();
+        assert_eq!((files.len(), cursor), (3, Some(1)));
+        // the cursor of the parent column is on the folder we came from
+        file_manager.dispatch(FileManagerAction::GoToParent).unwrap();
+        assert_eq!(loads(&receiver), vec![("/a".to_string(), true)]);
+        file_manager.consume_response(loaded("/a", &["x", "b", "z"], Some(&["a", "e"])));
+        assert_eq!(file_manager.parent_listing().map(|(_, cursor)| cursor), Some(Some(0)));
+        assert_eq!(file_manager.take_selection_after_load(), Some(1));
+        // the listing of the folder left is the parent column of its subfolders
+        file_manager.dispatch(FileManagerAction::Open(2)).unwrap();
+        assert_eq!(loads(&receiver), vec![("/a/z".to_string(), false)]);
+        file_manager.consume_response(loaded("/a/z", &[], None));
+        assert_eq!(file_manager.parent_listing().map(|(_, cursor)| cursor), Some(Some(2)));
+    }
+
+    #[test]
+    fn test_folder_sizes() {
+        let mut file_manager = file_manager("/a");
+        file_manager.files = vec![file("b", FileType::Folder), file("c", FileType::Folder)];
+        file_manager.sizes.insert(PathBuf::from("/a/b"), FolderSize::Measured(DiskUsage { apparent: 1000, disk: 4096, files: 1 }));
+        file_manager.sizes.insert(PathBuf::from("/a/c"), FolderSize::Measuring(0));
+        assert_eq!((file_manager.size_label(0).as_str(), file_manager.size_label(1).as_str()), ("1000B", "..."));
+        file_manager.dispatch(FileManagerAction::ToggleDiskUsage).unwrap();
+        assert_eq!(file_manager.size_label(0), "4.00KB");
+        // a change under a folder changes the totals of the folders containing it
+        for path in ["/", "/a", "/a/b", "/a/b/d", "/e"] {
+            file_manager.sizes.insert(PathBuf::from(path), FolderSize::Failed);
+        }
+        file_manager.forget_sizes(Path::new("/a/b"), false);
+        let mut paths = file_manager.sizes.keys().map(|path| path.to_str().unwrap()).collect::<Vec<&str>>();
+        paths.sort();
+        assert_eq!(paths, vec!["/a/b/d", "/a/c", "/e"]);
+        file_manager.forget_sizes(Path::new("/a"), true);
+        let mut paths = file_manager.sizes.keys().map(|path| path.to_str().unwrap()).collect::<Vec<&str>>();
+        paths.sort();
+        // the measures running are kept
+        assert_eq!(paths, vec!["/a/c", "/e"]);
+    }
+
+    #[test]
+    fn test_flatten_folder() {
+        let listing = vec![file("a", FileType::Folder), file("z.txt", FileType::File)];
+        let subtrees = HashMap::from([
+            (PathBuf::from("a"), vec![file("b", FileType::Folder), file("c.txt", FileType::File)]),
+            (PathBuf::from("a/b"), vec![file("d.txt", FileType::File)]),
+            // a subtree is only displayed under the row of its folder
+            (PathBuf::from("z.txt/e"), vec![file("f.txt", FileType::File)]),
+        ]);
+        let (mut files, mut guides) = (Vec::new(), Vec::new());
+        flatten_folder(&listing, Path::new(""), "", &subtrees, &mut files, &mut guides);
+        let names = files.iter().map(File::name).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["a", "a/b", "a/b/d.txt", "a/c.txt", "z.txt"]);
+        assert_eq!(guides, vec!["", "├─ ", "│  └─ ", "└─ ", ""]);
+        assert_eq!(files[2].base_name(), "d.txt");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::archive;
+use crate::utility::path::data_dir;
+
+// Name of the file storing the visited folders in the data folder
+static FRECENCY_FILE: &str = "frecency";
+// Sum of the ranks above which all the ranks are aged
+static MAX_TOTAL_RANK: f64 = 10000.0;
+// Factor applied to the ranks when they are aged
+static AGING_FACTOR: f64 = 0.9;
+// Rank under which an aged folder is forgotten
+static MIN_RANK: f64 = 1.0;
+static HOUR: u64 = 60 * 60;
+static DAY: u64 = 24 * HOUR;
+static WEEK: u64 = 7 * DAY;
+
+/// Folder visited by the user, its rank grows by one on each visit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub path: PathBuf,
+    pub rank: f64,
+    // seconds since the unix epoch
+    pub last_visit: u64,
+}
+
+impl Visit {
+    /// Weights the rank by the time elapsed since the last visit, the recent folders are favored
+    pub fn frecency(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_visit);
+        if elapsed < HOUR {
+            self.rank * 4.0
+        }
+        else if elapsed < DAY {
+            self.rank * 2.0
+        }
+        else if elapsed < WEEK {
+            self.rank / 2.0
+        }
+        else {
+            self.rank / 4.0
+        }
+    }
+
+    /// Parses a line of the frecency file: the rank, the last visit time and the path separated by tabulations
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let rank = fields.next()?.parse().ok()?;
+        let last_visit = fields.next()?.parse().ok()?;
+        let path = PathBuf::from(fields.next()?);
+        Some(Self { path, rank, last_visit })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.rank, self.last_visit, self.path.to_string_lossy())
+    }
+}
+
+/// Database of the visited folders ranked by frequency and recency, saved in the data folder after each visit
+pub struct Frecency {
+    visits: Vec<Visit>,
+    file: Option<PathBuf>,
+}
+
+impl Frecency {
+    /// Loads the frecency file and forgets the folders that no longer exist, a missing or unreadable file gives no visits
+    pub fn load() -> Self {
+        let file = data_dir().map(|folder| folder.join(FRECENCY_FILE));
+        let visits = file.as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| content.lines().filter_map(Visit::parse).filter(|visit| archive::folder_exists(&visit.path)).collect())
+            .unwrap_or_default();
+        Self { visits, file }
+    }
+
+    /// Records a visit of the folder and saves the database
+    pub fn record(&mut self, path: &Path) -> io::Result<()> {
+        self.visit(path, now());
+        self.save()
+    }
+
+    /// Returns the visited folders with their frecency, the highest first
+    pub fn ranked(&self) -> Vec<(&Visit, f64)> {
+        let now = now();
+        let mut ranked = self.visits.iter().map(|visit| (visit, visit.frecency(now))).collect::<Vec<(&Visit, f64)>>();
+        ranked.sort_by(|(_, first), (_, second)| second.total_cmp(first));
+        ranked
+    }
+}
+
+// private methods
+impl Frecency {
+    fn visit(&mut self, path: &Path, now: u64) {
+        match self.visits.iter_mut().find(|visit| visit.path == path) {
+            Some(visit) => {
+                visit.rank += 1.0;
+                visit.last_visit = now;
+            },
+            None => self.visits.push(Visit { path: path.to_path_buf(), rank: 1.0, last_visit: now }),
+        }
+        self.age();
+    }
+
+    /// Ages all the ranks once their sum is too high, the folders rarely visited end up forgotten
+    fn age(&mut self) {
+        if self.visits.iter().map(|visit| visit.rank).sum::<f64>() <= MAX_TOTAL_RANK {
+            return;
+        }
+        for visit in self.visits.iter_mut() {
+            visit.rank *= AGING_FACTOR;
+        }
+        self.visits.retain(|visit| visit.rank >= MIN_RANK);
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let file = self.file.as_ref().ok_or(io::Error::new(io::ErrorKind::NotFound, "No data folder"))?;
+        if let Some(folder) = file.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let content = self.visits.iter().map(|visit| visit.to_line() + "\n").collect::<String>();
+        fs::write(file, content)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+    use super::{Frecency, Visit, DAY, MAX_TOTAL_RANK};
+    #[test]
+    fn test_visit_line() {
+        let visit = Visit { path: PathBuf::from("/home/me/my projects"), rank: 2.5, last_visit: 1700000000 };
+        assert_eq!(Visit::parse(&visit.to_line()), Some(visit));
+        assert_eq!(Visit::parse("1\tnot a time\t/tmp"), None);
+    }
+
+    #[test]
+    fn test_frecency_aging() {
+        let mut frecency = Frecency { visits: Vec::new(), file: None };
+        frecency.visit(Path::new("/old"), 0);
+        frecency.visit(Path::new("/new"), 10 * DAY);
+        frecency.visit(Path::new("/new"), 10 * DAY);
+        assert_eq!(frecency.visits[0].frecency(10 * DAY), 0.25);
+        assert_eq!(frecency.visits[1].frecency(10 * DAY), 8.0);
+        // going over the max total rank ages every folder and forgets the ones under the min rank
+        frecency.visits[1].rank = MAX_TOTAL_RANK;
+        frecency.visit(Path::new("/new"), 10 * DAY);
+        assert_eq!(frecency.visits.len(), 1);
+        assert_eq!(frecency.visits[0].path, PathBuf::from("/new"));
+    }
+}
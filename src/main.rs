@@ -3,6 +3,13 @@ use file_manager::FileManager;
 
 mod file;
 
+mod archive;
+mod transfer;
+mod walk;
+mod disk_usage;
+mod bookmarks;
+mod frecency;
+
 mod app;
 use app::App;
 
@@ -10,6 +17,8 @@ mod utility;
 
 mod popup;
 
+mod preview;
+
 mod workers;
 
 use std::path::PathBuf;
@@ -23,11 +32,16 @@ fn main() -> std::io::Result<()> {
     // channels for communication between file manager -> light worker -> app
     let (file_manager_sender, light_worker_receiver) = mpsc::channel();
     let (light_worker_sender, app_receiver) = mpsc::channel();
+    // channels for communication between file manager -> heavy worker -> app
+    let (file_manager_heavy_sender, heavy_worker_receiver) = mpsc::channel();
+    let (heavy_worker_sender, app_heavy_receiver) = mpsc::channel();
 
-    let file_manager = FileManager::new(&path, 0, file_manager_sender);
-    let mut app = App::new(file_manager, app_receiver);
+    let file_manager = FileManager::new(&path, 0, file_manager_sender, file_manager_heavy_sender);
+    let mut app = App::new(file_manager, app_receiver, app_heavy_receiver);
 
     app.spawn_light_worker(Some(light_worker_sender), Some(light_worker_receiver));
+    app.spawn_heavy_worker(Some(heavy_worker_sender), Some(heavy_worker_receiver));
+    app.spawn_watcher();
 
     ratatui::run(|terminal| app.run(terminal))?;
     Ok(())
@@ -1,26 +1,295 @@
 use ratatui::{
-    DefaultTerminal, Frame, buffer::Buffer, layout::Rect, style::Stylize, symbols::border, text::{Line, Text}, widgets::{Block, List, Padding, Paragraph, StatefulWidget, Widget}
+    buffer::Buffer, layout::Rect, text::{Line, Span}, widgets::{Block, Clear, List, ListState, Paragraph, StatefulWidget, Widget}
 };
-use ratatui::style::{Style, Color};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
-use std::io;
-use std::time::Duration;
-use ratatui::layout::{Layout, Direction, Constraint};
-use std::path::PathBuf;
-use ratatui::widgets::ListState;
+use ratatui::style::{Style, Color, Modifier};
+use ratatui::layout::Constraint;
 use crate::file::FileType;
+use crate::preview::encoding::ENCODING_CHOICES;
+use crate::archive::{ConflictPolicy, CREATABLE_KINDS};
+use crate::transfer::TransferMode;
+use crate::utility::fuzzy;
+use crate::utility::path::expand;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Char size width of the archive popups
+static ARCHIVE_POPUP_WIDTH: u16 = 60;
+// Max number of completion candidates displayed under the path prompt
+static MAX_DISPLAYED_CANDIDATES: usize = 10;
 
 pub enum Popup {
+    #[allow(dead_code)]
     Create{file_type: FileType, name: String},
+    Encoding{selected: usize},
+    // an empty name extracts into the current folder
+    Extract{name: String, policy: ConflictPolicy},
+    // the kind is an index of the creatable archive kinds
+    Compress{name: String, kind: usize, policy: ConflictPolicy},
+    // the destination defaults to the folder of the other pane
+    Transfer{mode: TransferMode, destination: String, policy: ConflictPolicy},
+    Bookmarks(Picker),
+    Jump(Picker),
+    GoTo(PathPrompt),
+}
+
+/// Path typed by the user, completed with Tab against the content of the folder being typed
+#[derive(Default)]
+pub struct PathPrompt {
+    input: String,
+    // typed folder the candidates complete, kept as typed
+    base: String,
+    // entries of the typed folder starting with the last typed component, the folders end with a slash
+    candidates: Vec<String>,
+    // candidate inserted by the last Tab, None until the candidates are cycled
+    cycled: Option<usize>,
+    error: Option<String>,
+}
+
+impl PathPrompt {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn push(&mut self, character: char) {
+        self.input.push(character);
+        self.reset();
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.reset();
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /// Completes the last component of the input with the entries of its folder, the first Tab inserts the common prefix of the
+    /// candidates and the next ones cycle through them
+    pub fn complete(&mut self, current: &Path) {
+        if self.candidates.len() > 1 {
+            let next = self.cycled.map(|index| (index + 1) % self.candidates.len()).unwrap_or(0);
+            self.input = format!("{}{}", self.base, self.candidates[next]);
+            self.cycled = Some(next);
+            return;
+        }
+        let (base, prefix) = match self.input.rfind('/') {
+            Some(index) => self.input.split_at(index + 1),
+            None => ("", self.input.as_str()),
+        };
+        let folder = match expand(base, current) {
+            Ok(folder) => folder,
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            },
+        };
+        let mut candidates = fs::read_dir(folder).map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // hidden entries are only completed once their dot is typed
+                if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                    return None;
+                }
+                Some(if entry.path().is_dir() { name + "/" } else { name })
+            })
+            .collect::<Vec<String>>()).unwrap_or_default();
+        candidates.sort();
+        self.base = base.to_string();
+        self.cycled = None;
+        self.error = None;
+        match candidates.as_slice() {
+            [] => self.error = Some(String::from("No completion")),
+            [candidate] => {
+                self.input = format!("{}{}", self.base, candidate);
+                self.candidates.clear();
+            },
+            [first, others @ ..] => {
+                let common = others.iter().fold(first.as_str(), |common, candidate| {
+                    let length = common.chars().zip(candidate.chars()).take_while(|(common, other)| common == other).map(|(common, _)| common.len_utf8()).sum();
+                    &common[..length]
+                });
+                self.input = format!("{}{}", self.base, common);
+                self.candidates = candidates;
+            },
+        }
+    }
+
+    fn reset(&mut self) {
+        self.candidates.clear();
+        self.cycled = None;
+        self.error = None;
+    }
+}
+
+/// Entry of a picker, the rank is added to the fuzzy score to order the matches
+pub struct PickerItem {
+    pub label: String,
+    pub path: PathBuf,
+    pub missing: bool,
+    pub rank: i64,
+}
+
+/// List of folders filtered and ordered by a fuzzy search on their label and path
+pub struct Picker {
+    title: &'static str,
+    query: String,
+    items: Vec<PickerItem>,
+    // indexes of the items matching the query, best match first
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(title: &'static str, items: Vec<PickerItem>) -> Self {
+        let mut picker = Self { title, query: String::new(), items, matches: Vec::new(), selected: 0 };
+        picker.update_matches();
+        picker
+    }
+
+    pub fn push(&mut self, character: char) {
+        self.query.push(character);
+        self.update_matches();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.update_matches();
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected as isize + delta).rem_euclid(self.matches.len() as isize) as usize;
+        }
+    }
+
+    /// Returns the index of the selected item in the items the picker was built with
+    pub fn selected_index(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    pub fn selected_item(&self) -> Option<&PickerItem> {
+        self.selected_index().map(|index| &self.items[index])
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored = self.items.iter().enumerate()
+            .filter_map(|(index, item)| {
+                let score = fuzzy::score(&self.query, &format!("{} {}", item.label, item.path.to_string_lossy()))?;
+                Some((index, score + item.rank))
+            })
+            .collect::<Vec<(usize, i64)>>();
+        scored.sort_by_key(|(_, score)| -score);
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.selected = 0;
+    }
 }
 
 impl Widget for &mut Popup {
     fn render(self, area: Rect, buf: &mut Buffer) {
         match self {
-            Popup::Create{file_type, name} => {
+            Popup::Create{file_type: _, name: _} => {
                 let display_square = area.centered(Constraint::Length(20), Constraint::Length(20));
-                let display_square = Block::bordered().border_style(Style::default().fg(Color::White)).render(display_square, buf);
+                Block::bordered().border_style(Style::default().fg(Color::White)).render(display_square, buf);
+            },
+            Popup::Encoding{selected} => {
+                let display_square = area.centered(Constraint::Length(30), Constraint::Length(ENCODING_CHOICES.len() as u16 + 2));
+                Clear.render(display_square, buf);
+                let items = ENCODING_CHOICES.iter().map(|choice| match choice {
+                    Some(encoding) => Line::from(encoding.name()),
+                    None => Line::from("Auto detection"),
+                }).collect::<Vec<Line>>();
+                let list = List::new(items)
+                    .block(Block::bordered().title(Line::from(" Encoding ").centered()).border_style(Style::default().fg(Color::White)))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                let mut state = ListState::default().with_selected(Some(*selected));
+                StatefulWidget::render(list, display_square, buf, &mut state);
+            },
+            Popup::Extract{name, policy} => {
+                let lines = vec![
+                    field_line("Folder:      ", format!("{}_", name)),
+                    field_line("On conflict: ", policy.name().to_string()),
+                    Line::default(),
+                    Line::styled("Empty folder extracts here, Tab conflict policy", Style::default().fg(Color::DarkGray)),
+                ];
+                render_form(" Extract ", lines, area, buf);
+            },
+            Popup::Compress{name, kind, policy} => {
+                let lines = vec![
+                    field_line("Name:        ", format!("{}{}_", name, CREATABLE_KINDS[*kind].extension())),
+                    field_line("Format:      ", CREATABLE_KINDS[*kind].name().to_string()),
+                    field_line("On conflict: ", policy.name().to_string()),
+                    Line::styled("Up/Down format, Tab conflict policy", Style::default().fg(Color::DarkGray)),
+                ];
+                render_form(" Compress ", lines, area, buf);
+            },
+            Popup::Transfer{mode, destination, policy} => {
+                let lines = vec![
+                    field_line("Destination: ", format!("{}_", destination)),
+                    field_line("On conflict: ", policy.name().to_string()),
+                    Line::default(),
+                    Line::styled("Tab conflict policy", Style::default().fg(Color::DarkGray)),
+                ];
+                let title = match mode {
+                    TransferMode::Copy => " Copy ",
+                    TransferMode::Move => " Move ",
+                };
+                render_form(title, lines, area, buf);
+            },
+            Popup::Bookmarks(picker) | Popup::Jump(picker) => picker.render(area, buf),
+            Popup::GoTo(prompt) => {
+                let mut lines = vec![field_line("Path: ", format!("{}_", prompt.input))];
+                match &prompt.error {
+                    Some(error) => lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red))),
+                    None => lines.push(Line::styled("Tab to complete, ~ and $VAR are expanded", Style::default().fg(Color::DarkGray))),
+                }
+                for (index, candidate) in prompt.candidates.iter().enumerate().take(MAX_DISPLAYED_CANDIDATES) {
+                    let style = if prompt.cycled == Some(index) { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default() };
+                    let style = if candidate.ends_with('/') { style.fg(Color::Blue) } else { style };
+                    lines.push(Line::styled(candidate.clone(), style));
+                }
+                if prompt.candidates.len() > MAX_DISPLAYED_CANDIDATES {
+                    lines.push(Line::styled(format!("... {} more", prompt.candidates.len() - MAX_DISPLAYED_CANDIDATES), Style::default().fg(Color::DarkGray)));
+                }
+                render_form(" Go to ", lines, area, buf);
             },
         }
     }
-}
\ No newline at end of file
+}
+
+fn field_line(label: &'static str, value: String) -> Line<'static> {
+    Line::from(vec![Span::styled(label, Style::default().fg(Color::Yellow)), Span::raw(value)])
+}
+
+/// Renders the lines of a form popup centered in a bordered block
+fn render_form(title: &'static str, lines: Vec<Line<'static>>, area: Rect, buf: &mut Buffer) {
+    let display_square = area.centered(Constraint::Length(ARCHIVE_POPUP_WIDTH), Constraint::Length(lines.len() as u16 + 2));
+    Clear.render(display_square, buf);
+    Paragraph::new(lines)
+        .block(Block::bordered().title(Line::from(title).centered()).border_style(Style::default().fg(Color::White)))
+        .render(display_square, buf);
+}
+
+impl Picker {
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let display_square = area.centered(Constraint::Percentage(70), Constraint::Percentage(60));
+        Clear.render(display_square, buf);
+        let block = Block::bordered().title(Line::from(self.title).centered()).border_style(Style::default().fg(Color::White));
+        let inner = block.inner(display_square);
+        block.render(display_square, buf);
+        let [query_area, list_area] = inner.layout(&ratatui::layout::Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]));
+        Line::from(vec![Span::styled("> ", Style::default().fg(Color::Yellow)), Span::raw(format!("{}_", self.query))]).render(query_area, buf);
+        let items = self.matches.iter().map(|index| {
+            let item = &self.items[*index];
+            let mut spans = vec![Span::raw(format!("{}  ", item.label)), Span::styled(item.path.to_string_lossy().to_string(), Style::default().fg(Color::DarkGray))];
+            if item.missing {
+                spans.push(Span::styled(" (missing)", Style::default().fg(Color::Red)));
+            }
+            Line::from(spans)
+        }).collect::<Vec<Line>>();
+        let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(self.selected_index().map(|_| self.selected));
+        StatefulWidget::render(list, list_area, buf, &mut state);
+    }
+}
@@ -0,0 +1,64 @@
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}
+};
+use ratatui::style::{Style, Color};
+use chrono::{DateTime, Local};
+use crate::archive::{self, ArchiveEntry, ArchiveKind};
+use crate::file::Size;
+use crate::utility::string::expand_or_truncate;
+
+// Char size width of the size column of an archive entry
+static ENTRY_SIZE_WIDTH: usize = 10;
+// Char size width of the modified time column of an archive entry
+static ENTRY_MODIFIED_WIDTH: usize = 18;
+
+/// Preview of an archive listing its entries with their mode, size and modified time
+pub struct ArchivePreview {
+    kind: ArchiveKind,
+    entries: usize,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    text: Text<'static>,
+    scroll: usize,
+}
+
+impl ArchivePreview {
+    /// Builds the listing, the compressed size is the size of the archive file itself
+    pub fn new(kind: ArchiveKind, entries: &[ArchiveEntry], compressed_size: u64) -> Self {
+        let uncompressed_size = entries.iter().filter(|entry| !entry.is_dir).map(|entry| entry.size).sum();
+        let lines = entries.iter().map(|entry| {
+            let mode = match entry.mode {
+                Some(mode) => archive::mode_to_string(mode, entry.is_dir),
+                None if entry.is_dir => "d---------".to_string(),
+                None => "----------".to_string(),
+            };
+            let size = if entry.is_dir { String::new() } else { Size::from(entry.size).to_string() };
+            let modified = entry.modified.map(|time| DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+            let path = if entry.is_dir {
+                Span::styled(format!("{}/", entry.path), Style::default().fg(Color::Blue))
+            }
+            else {
+                Span::raw(entry.path.clone())
+            };
+            Line::from(vec![
+                Span::styled(format!("{} ", mode), Style::default().fg(Color::DarkGray)),
+                Span::raw(format!("{:>width$} ", size, width = ENTRY_SIZE_WIDTH - 1)),
+                Span::styled(expand_or_truncate(modified, ENTRY_MODIFIED_WIDTH), Style::default().fg(Color::Yellow)),
+                path,
+            ])
+        }).collect::<Vec<Line>>();
+        Self { kind, entries: entries.len(), compressed_size, uncompressed_size, text: Text::from(lines), scroll: 0 }
+    }
+
+    pub fn title(&self) -> String {
+        format!(" Preview - {} {} entries, {} compressed, {} uncompressed ", self.kind.name(), self.entries, Size::from(self.compressed_size), Size::from(self.uncompressed_size))
+    }
+
+    pub fn scroll(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta).min(self.text.lines.len().saturating_sub(1));
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        super::render_lines(&self.text, self.scroll, area, buf);
+    }
+}
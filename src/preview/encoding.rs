@@ -0,0 +1,108 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+
+// Number of bytes sampled at the start of a content to detect UTF-16 without BOM
+static UTF16_SAMPLE_SIZE: usize = 4096;
+// Min ratio of null bytes on one side of the code units for a content to be considered UTF-16
+static UTF16_NULL_RATIO: f32 = 0.3;
+
+/// Encodings proposed to re-read a file with, None stands for the automatic detection
+pub static ENCODING_CHOICES: &[Option<&'static Encoding>] = &[
+    None,
+    Some(UTF_8),
+    Some(UTF_16LE),
+    Some(UTF_16BE),
+    Some(encoding_rs::WINDOWS_1252),
+    Some(encoding_rs::ISO_8859_15),
+    Some(encoding_rs::ISO_8859_2),
+    Some(encoding_rs::WINDOWS_1250),
+    Some(encoding_rs::WINDOWS_1251),
+    Some(encoding_rs::KOI8_R),
+    Some(encoding_rs::SHIFT_JIS),
+    Some(encoding_rs::EUC_JP),
+    Some(encoding_rs::GBK),
+    Some(encoding_rs::GB18030),
+    Some(encoding_rs::BIG5),
+    Some(encoding_rs::EUC_KR),
+];
+
+/// Detects the encoding of the content, first from its BOM, then from the null bytes layout of UTF-16 and finally from a statistical guess,
+/// returns None if the content looks binary
+pub fn detect(bytes: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+    if let Some(encoding) = detect_utf16(bytes) {
+        return Some(encoding);
+    }
+    // a null byte never appears in a text outside of UTF-16
+    if bytes.contains(&0) {
+        return None;
+    }
+    if is_utf8(bytes) {
+        return Some(UTF_8);
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    Some(detector.guess(None, Utf8Detection::Allow))
+}
+
+/// Decodes the content with the encoding, the BOM is skipped if it belongs to this encoding and malformed sequences are replaced
+pub fn decode(bytes: &[u8], encoding: &'static Encoding) -> String {
+    let bytes = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, bom_length)) if bom_encoding == encoding => &bytes[bom_length..],
+        _ => bytes,
+    };
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Returns true if the content is valid UTF-8, a character cut at the end of the content (bounded read) is tolerated
+fn is_utf8(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+/// Detects UTF-16 without BOM, mostly ASCII text encoded in UTF-16 has a null byte in every code unit on the same side
+fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SAMPLE_SIZE)];
+    let units = sample.len() / 2;
+    if units == 0 {
+        return None;
+    }
+    let even_nulls = sample.chunks_exact(2).filter(|unit| unit[0] == 0 && unit[1] != 0).count();
+    let odd_nulls = sample.chunks_exact(2).filter(|unit| unit[0] != 0 && unit[1] == 0).count();
+    let threshold = (units as f32 * UTF16_NULL_RATIO) as usize;
+    if odd_nulls > threshold && even_nulls == 0 {
+        Some(UTF_16LE)
+    }
+    else if even_nulls > threshold && odd_nulls == 0 {
+        Some(UTF_16BE)
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect, decode};
+    use encoding_rs::{UTF_8, UTF_16LE, UTF_16BE, WINDOWS_1252};
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"\xEF\xBB\xBFhello"), Some(UTF_8));
+        assert_eq!(detect(b"\xFF\xFEh\x00i\x00"), Some(UTF_16LE));
+        assert_eq!(detect(b"h\x00e\x00l\x00l\x00o\x00"), Some(UTF_16LE));
+        assert_eq!(detect(b"\x00h\x00e\x00l\x00l\x00o"), Some(UTF_16BE));
+        assert_eq!(detect("caf\u{e9}".as_bytes()), Some(UTF_8));
+        assert_eq!(detect(b"nom;pr\xe9nom;ann\xe9e\nL\xe9a;Ren\xe9e;1987\n"), Some(WINDOWS_1252));
+        assert_eq!(detect(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x00"), None);
+    }
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xEF\xBB\xBFhello", UTF_8), "hello");
+        assert_eq!(decode(b"\xFF\xFEh\x00i\x00", UTF_16LE), "hi");
+        assert_eq!(decode(b"caf\xe9", WINDOWS_1252), "caf\u{e9}");
+    }
+}
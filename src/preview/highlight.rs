@@ -0,0 +1,122 @@
+use std::path::Path;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+// Max byte size of a content to highlight, bigger contents are previewed as plain text
+pub static HIGHLIGHT_MAX_SIZE: usize = 256 * 1024;
+// Name of the bundled theme used to colorize the previews
+static THEME_NAME: &str = "base16-eighties.dark";
+
+pub struct Highlighter {
+    syntax_set: SyntaxSet,
+    theme: Theme,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let mut theme_set = ThemeSet::load_defaults();
+        let theme = theme_set.themes.remove(THEME_NAME).unwrap_or_default();
+        Self { syntax_set, theme }
+    }
+
+    /// Highlights the content using the syntax found from the path extension or the first line of the content (shebang),
+    /// returns None if no syntax matches or if the content is too big to be highlighted
+    pub fn highlight(&self, path: &Path, content: &str) -> Option<Text<'static>> {
+        if content.len() > HIGHLIGHT_MAX_SIZE {
+            return None;
+        }
+        let syntax = self.find_syntax(path, content)?;
+        self.highlight_with(syntax, content)
+    }
+
+    /// Highlights the content using the syntax matching the token, a language name or a file extension (e.g. code blocks languages)
+    pub fn highlight_token(&self, token: &str, content: &str) -> Option<Text<'static>> {
+        if content.len() > HIGHLIGHT_MAX_SIZE {
+            return None;
+        }
+        let syntax = self.syntax_set.find_syntax_by_token(token)?;
+        self.highlight_with(syntax, content)
+    }
+
+    fn find_syntax(&self, path: &Path, content: &str) -> Option<&SyntaxReference> {
+        let by_extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.syntax_set.find_syntax_by_extension(extension));
+        // some syntaxes are registered by their full file name (Makefile, Dockerfile, ...)
+        let by_name = || path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| self.syntax_set.find_syntax_by_extension(name));
+        let by_first_line = || content.lines().next().and_then(|line| self.syntax_set.find_syntax_by_first_line(line));
+        by_extension.or_else(by_name).or_else(by_first_line)
+    }
+
+    fn highlight_with(&self, syntax: &SyntaxReference, content: &str) -> Option<Text<'static>> {
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(content) {
+            let ranges = highlighter.highlight_line(line, &self.syntax_set).ok()?;
+            let spans = ranges.into_iter()
+                .map(|(style, text)| Span::styled(super::expand_tabs(text.trim_end_matches(['\n', '\r'])), to_ratatui_style(style)))
+                .collect::<Vec<Span>>();
+            lines.push(Line::from(spans));
+        }
+        Some(Text::from(lines))
+    }
+}
+
+/// Converts a syntect style into a ratatui one, the theme background is ignored to keep the terminal one
+fn to_ratatui_style(style: syntect::highlighting::Style) -> Style {
+    let mut ratatui_style = Style::default().fg(Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        ratatui_style = ratatui_style.add_modifier(Modifier::UNDERLINED);
+    }
+    ratatui_style
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use ratatui::style::Style;
+    use crate::preview::Preview;
+    use super::{Highlighter, HIGHLIGHT_MAX_SIZE};
+
+    #[test]
+    fn test_find_syntax() {
+        let highlighter = Highlighter::new();
+        let syntax = |path: &str, content: &str| highlighter.find_syntax(Path::new(path), content).map(|syntax| syntax.name.as_str());
+        assert_eq!(syntax("main.rs", ""), Some("Rust"));
+        assert_eq!(syntax("Makefile", ""), Some("Makefile"));
+        // a script without extension is recognized by its shebang
+        assert_eq!(syntax("run", "#!/usr/bin/env python3\nprint(1)\n"), Some("Python"));
+        assert_eq!(syntax("run", "#!/bin/bash\necho 1\n"), Some("Bourne Again Shell (bash)"));
+        assert_eq!(syntax("notes", "some words\n"), None);
+    }
+
+    #[test]
+    fn test_highlight_max_size() {
+        let highlighter = Highlighter::new();
+        let content = "fn main() {}\n";
+        assert!(highlighter.highlight(Path::new("main.rs"), content).is_some());
+        // a bigger content is previewed as plain text
+        let content = content.repeat(HIGHLIGHT_MAX_SIZE / content.len() + 1);
+        assert_eq!(highlighter.highlight(Path::new("main.rs"), &content), None);
+        match Preview::text(Path::new("main.rs"), &content, None, false, &highlighter) {
+            Preview::Text { text, .. } => {
+                assert_eq!(text.lines.len(), content.lines().count());
+                assert!(text.lines.iter().flat_map(|line| &line.spans).all(|span| span.style == Style::default()));
+            },
+            _ => panic!("not a text preview"),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::Cursor;
+use image::{ImageFormat, ImageReader, Rgba};
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}, widgets::{Paragraph, Widget}
+};
+use ratatui::style::{Style, Color};
+
+// Upper half block, its foreground draws the top pixel and its background the bottom one
+static HALF_BLOCK: &str = "▀";
+// Alpha under which a pixel is considered transparent and left to the terminal background
+static TRANSPARENT_ALPHA: u8 = 64;
+
+/// Colors the terminal can display
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorMode {
+    TrueColor,
+    Indexed,
+}
+
+impl ColorMode {
+    /// Detects the truecolor support from the `COLORTERM` variable, the 256 colors palette is used otherwise
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM") {
+            Ok(value) if value.contains("truecolor") || value.contains("24bit") => ColorMode::TrueColor,
+            _ => ColorMode::Indexed,
+        }
+    }
+
+    fn color(&self, pixel: &Rgba<u8>) -> Color {
+        let [red, green, blue, alpha] = pixel.0;
+        if alpha < TRANSPARENT_ALPHA {
+            return Color::Reset;
+        }
+        match self {
+            ColorMode::TrueColor => Color::Rgb(red, green, blue),
+            ColorMode::Indexed => Color::Indexed(to_ansi_256(red, green, blue)),
+        }
+    }
+}
+
+/// Maps a color to the closest one of the 6x6x6 cube or of the grayscale ramp of the 256 colors palette
+fn to_ansi_256(red: u8, green: u8, blue: u8) -> u8 {
+    let to_cube = |value: u8| if value < 48 { 0 } else if value < 115 { 1 } else { (value - 35) / 40 };
+    let (cube_red, cube_green, cube_blue) = (to_cube(red), to_cube(green), to_cube(blue));
+    let cube_value = |level: u8| if level == 0 { 0 } else { 55 + level as i32 * 40 };
+    let cube_distance = [(red, cube_red), (green, cube_green), (blue, cube_blue)].iter()
+        .map(|(value, level)| (*value as i32 - cube_value(*level)).pow(2))
+        .sum::<i32>();
+    let average = (red as i32 + green as i32 + blue as i32) / 3;
+    let gray_level = ((average - 8).max(0) / 10).min(23);
+    let gray_value = 8 + gray_level * 10;
+    let gray_distance = [red, green, blue].iter().map(|value| (*value as i32 - gray_value).pow(2)).sum::<i32>();
+    if gray_distance < cube_distance {
+        232 + gray_level as u8
+    }
+    else {
+        16 + 36 * cube_red + 6 * cube_green + cube_blue
+    }
+}
+
+/// Returns the image format of the content if it's one that can be previewed
+pub fn detect_format(bytes: &[u8]) -> Option<ImageFormat> {
+    match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP)) => Some(format),
+        _ => None,
+    }
+}
+
+/// Preview of an image downscaled to the preview section, each cell draws two pixels stacked vertically
+pub struct ImagePreview {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    text: Text<'static>,
+}
+
+impl ImagePreview {
+    /// Decodes the image and downscales it to fit in the given cells area
+    pub fn new(bytes: &[u8], format: ImageFormat, area: (u16, u16), color_mode: ColorMode) -> Result<Self, String> {
+        let image = ImageReader::with_format(Cursor::new(bytes), format).decode().map_err(|error| error.to_string())?;
+        let (width, height) = (image.width(), image.height());
+        let thumbnail = image.thumbnail(area.0.max(1) as u32, area.1.max(1) as u32 * 2).to_rgba8();
+        let transparent = Rgba([0, 0, 0, 0]);
+        let mut lines = Vec::new();
+        for y in (0..thumbnail.height()).step_by(2) {
+            let mut spans: Vec<Span> = Vec::new();
+            let mut current: Option<(Style, String)> = None;
+            for x in 0..thumbnail.width() {
+                let top = thumbnail.get_pixel(x, y);
+                let bottom = if y + 1 < thumbnail.height() { thumbnail.get_pixel(x, y + 1) } else { &transparent };
+                let style = Style::default().fg(color_mode.color(top)).bg(color_mode.color(bottom));
+                // consecutive cells with the same colors are merged in a single span
+                match &mut current {
+                    Some((current_style, cells)) if *current_style == style => cells.push_str(HALF_BLOCK),
+                    _ => {
+                        if let Some((style, cells)) = current.take() {
+                            spans.push(Span::styled(cells, style));
+                        }
+                        current = Some((style, HALF_BLOCK.to_string()));
+                    },
+                }
+            }
+            if let Some((style, cells)) = current {
+                spans.push(Span::styled(cells, style));
+            }
+            lines.push(Line::from(spans));
+        }
+        Ok(Self { format, width, height, text: Text::from(lines) })
+    }
+
+    pub fn title(&self) -> String {
+        let format = self.format.extensions_str().first().map(|extension| extension.to_uppercase()).unwrap_or_default();
+        format!(" Preview - {} {}x{} ", format, self.width, self.height)
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        Paragraph::new(self.text.clone()).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_ansi_256;
+    #[test]
+    fn test_to_ansi_256() {
+        assert_eq!(to_ansi_256(0, 0, 0), 16);
+        assert_eq!(to_ansi_256(255, 255, 255), 231);
+        assert_eq!(to_ansi_256(255, 0, 0), 196);
+        assert_eq!(to_ansi_256(128, 128, 128), 244);
+    }
+}
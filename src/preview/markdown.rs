@@ -0,0 +1,413 @@
+use std::path::Path;
+use encoding_rs::Encoding;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}, widgets::{Paragraph, Widget, Wrap}
+};
+use ratatui::style::{Style, Color, Modifier};
+use crate::preview::Highlighter;
+
+/// Returns true if the path extension is one of a markdown file
+pub fn is_markdown(path: &Path) -> bool {
+    matches!(path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref(), Some("md") | Some("markdown") | Some("mdown") | Some("mkd"))
+}
+
+/// Preview of a markdown file, rendered as styled text or displayed raw
+pub struct MarkdownPreview {
+    encoding: Option<&'static Encoding>,
+    rendered: Text<'static>,
+    raw: Text<'static>,
+    show_raw: bool,
+    scroll: usize,
+}
+
+impl MarkdownPreview {
+    pub fn new(content: &str, raw: Text<'static>, encoding: Option<&'static Encoding>, highlighter: &Highlighter) -> Self {
+        let rendered = Renderer::new(highlighter).render(content);
+        Self { encoding, rendered, raw, show_raw: false, scroll: 0 }
+    }
+
+    pub fn title(&self) -> String {
+        let view = if self.show_raw { "raw" } else { "rendered" };
+        match self.encoding {
+            Some(encoding) => format!(" Preview - {} - Markdown ({}) ", encoding.name(), view),
+            None => format!(" Preview - Markdown ({}) ", view),
+        }
+    }
+
+    pub fn cycle_view(&mut self) {
+        self.show_raw = !self.show_raw;
+        self.scroll = 0;
+    }
+
+    pub fn scroll(&mut self, delta: isize) {
+        let length = if self.show_raw { self.raw.lines.len() } else { self.rendered.lines.len() };
+        self.scroll = self.scroll.saturating_add_signed(delta).min(length.saturating_sub(1));
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        if self.show_raw {
+            super::render_lines(&self.raw, self.scroll, area, buf);
+            return;
+        }
+        // the rendered paragraphs are wrapped, every line takes at least one row so the visible lines are enough
+        let visible = self.rendered.lines.iter().skip(self.scroll).take(area.height as usize).cloned().collect::<Vec<Line>>();
+        Paragraph::new(visible).wrap(Wrap { trim: false }).render(area, buf);
+    }
+}
+
+/// List being rendered, ordered lists keep the number of their next item,
+/// the marker of the current item is only displayed on its first line
+struct List {
+    next_number: Option<u64>,
+    marker: String,
+    marker_displayed: bool,
+}
+
+/// Table being rendered, the rows are buffered to align the columns once the table ends
+#[derive(Default)]
+struct TableBuffer {
+    rows: Vec<Vec<String>>,
+    header_rows: usize,
+}
+
+/// Converts the markdown events into styled lines
+struct Renderer<'a> {
+    highlighter: &'a Highlighter,
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    lists: Vec<List>,
+    quote_depth: usize,
+    code_block: Option<(String, String)>,
+    table: Option<TableBuffer>,
+    links: Vec<String>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(highlighter: &'a Highlighter) -> Self {
+        Self {
+            highlighter,
+            lines: Vec::new(),
+            spans: Vec::new(),
+            styles: vec![Style::default()],
+            lists: Vec::new(),
+            quote_depth: 0,
+            code_block: None,
+            table: None,
+            links: Vec::new(),
+        }
+    }
+
+    fn render(mut self, content: &str) -> Text<'static> {
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+        for event in Parser::new_ext(content, options) {
+            self.handle_event(event);
+        }
+        self.flush();
+        // the links are listed as footnotes at the end of the document
+        if !self.links.is_empty() {
+            self.blank_line();
+            self.lines.push(Line::styled("Links:", Style::default().add_modifier(Modifier::BOLD)));
+            for (index, link) in self.links.iter().enumerate() {
+                self.lines.push(Line::from(vec![Span::styled(format!("[{}] ", index + 1), Style::default().fg(Color::DarkGray)), Span::styled(link.clone(), Style::default().fg(Color::Blue))]));
+            }
+        }
+        Text::from(self.lines)
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut self.code_block {
+                    code.push_str(&text);
+                }
+                else if let Some(table) = &mut self.table {
+                    if let Some(cell) = table.rows.last_mut().and_then(|row| row.last_mut()) {
+                        cell.push_str(&text);
+                    }
+                }
+                else {
+                    self.push(text.to_string());
+                }
+            },
+            Event::Code(code) => {
+                if let Some(cell) = self.table.as_mut().and_then(|table| table.rows.last_mut()).and_then(|row| row.last_mut()) {
+                    cell.push_str(&code);
+                }
+                else {
+                    self.spans.push(Span::styled(code.to_string(), Style::default().fg(Color::Yellow)));
+                }
+            },
+            Event::Html(html) | Event::InlineHtml(html) => {
+                self.spans.push(Span::styled(html.trim_end().to_string(), Style::default().fg(Color::DarkGray)));
+                if html.ends_with('\n') {
+                    self.flush();
+                }
+            },
+            Event::SoftBreak => self.push(" ".to_string()),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.lines.push(Line::styled("─".repeat(40), Style::default().fg(Color::DarkGray)));
+                self.blank_line();
+            },
+            Event::TaskListMarker(checked) => self.push(if checked { "[x] ".to_string() } else { "[ ] ".to_string() }),
+            _ => {},
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                let style = match level {
+                    HeadingLevel::H1 => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                    HeadingLevel::H2 => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                    _ => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                };
+                self.styles.push(style);
+            },
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+                self.styles.push(self.style().fg(Color::Gray).add_modifier(Modifier::ITALIC));
+            },
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split([' ', ',']).next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block = Some((language, String::new()));
+            },
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(List { next_number: start, marker: String::new(), marker_displayed: true });
+            },
+            Tag::Item => {
+                self.flush();
+                if let Some(list) = self.lists.last_mut() {
+                    list.marker = match &mut list.next_number {
+                        Some(number) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        },
+                        None => "• ".to_string(),
+                    };
+                    list.marker_displayed = false;
+                }
+            },
+            Tag::Table(_) => {
+                self.flush();
+                self.table = Some(TableBuffer::default());
+            },
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            },
+            Tag::TableCell => {
+                if let Some(row) = self.table.as_mut().and_then(|table| table.rows.last_mut()) {
+                    row.push(String::new());
+                }
+            },
+            Tag::Emphasis => self.styles.push(self.style().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.styles.push(self.style().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.styles.push(self.style().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } => {
+                self.links.push(dest_url.to_string());
+                self.styles.push(self.style().fg(Color::Blue).add_modifier(Modifier::UNDERLINED));
+            },
+            Tag::Image { dest_url, .. } => {
+                self.links.push(dest_url.to_string());
+                self.styles.push(self.style().fg(Color::Blue));
+                self.push("[image: ".to_string());
+            },
+            _ => {},
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush();
+                // paragraphs of tight list items are not separated
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            },
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush();
+                self.blank_line();
+            },
+            TagEnd::BlockQuote(_) => {
+                self.styles.pop();
+                self.flush();
+                self.quote_depth -= 1;
+                if self.quote_depth == 0 {
+                    self.blank_line();
+                }
+            },
+            TagEnd::CodeBlock => {
+                if let Some((language, code)) = self.code_block.take() {
+                    let text = self.highlighter.highlight_token(&language, &code).unwrap_or_else(|| super::plain_text(&code));
+                    for line in text.lines {
+                        let mut spans = self.prefix();
+                        spans.push(Span::raw("  "));
+                        spans.extend(line.spans);
+                        self.lines.push(Line::from(spans));
+                    }
+                }
+                self.blank_line();
+            },
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            },
+            TagEnd::Item => {
+                self.flush();
+            },
+            TagEnd::TableHead => {
+                if let Some(table) = &mut self.table {
+                    table.header_rows = table.rows.len();
+                }
+            },
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.render_table(table);
+                }
+                self.blank_line();
+            },
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            },
+            TagEnd::Link => {
+                self.styles.pop();
+                self.spans.push(Span::styled(format!("[{}]", self.links.len()), Style::default().fg(Color::DarkGray)));
+            },
+            TagEnd::Image => {
+                self.push("]".to_string());
+                self.styles.pop();
+                self.spans.push(Span::styled(format!("[{}]", self.links.len()), Style::default().fg(Color::DarkGray)));
+            },
+            _ => {},
+        }
+    }
+
+    fn render_table(&mut self, table: TableBuffer) {
+        let columns = table.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let widths = (0..columns).map(|column| table.rows.iter().filter_map(|row| row.get(column)).map(|cell| cell.chars().count()).max().unwrap_or(0)).collect::<Vec<usize>>();
+        for (index, row) in table.rows.iter().enumerate() {
+            let cells = widths.iter().enumerate().map(|(column, width)| {
+                let cell = row.get(column).map(String::as_str).unwrap_or_default();
+                format!("{}{}", cell, " ".repeat(width.saturating_sub(cell.chars().count())))
+            }).collect::<Vec<String>>();
+            let style = if index < table.header_rows { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() };
+            let mut spans = self.prefix();
+            spans.push(Span::styled(cells.join(" │ "), style));
+            self.lines.push(Line::from(spans));
+            if index + 1 == table.header_rows {
+                let separator = widths.iter().map(|width| "─".repeat(*width)).collect::<Vec<String>>().join("─┼─");
+                let mut spans = self.prefix();
+                spans.push(Span::styled(separator, Style::default().fg(Color::DarkGray)));
+                self.lines.push(Line::from(spans));
+            }
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn push(&mut self, text: String) {
+        let style = self.style();
+        self.spans.push(Span::styled(text, style));
+    }
+
+    /// Builds the prefix of a new line from the block quotes and the list items it's in,
+    /// the lines of a list item are indented by the markers of the items containing it
+    fn prefix(&mut self) -> Vec<Span<'static>> {
+        let mut prefix = vec![Span::styled("│ ".repeat(self.quote_depth), Style::default().fg(Color::DarkGray))];
+        let depth = self.lists.len();
+        for (level, list) in self.lists.iter_mut().enumerate() {
+            if level + 1 == depth && !list.marker_displayed {
+                prefix.push(Span::styled(list.marker.clone(), Style::default().fg(Color::Yellow)));
+                list.marker_displayed = true;
+            }
+            else {
+                prefix.push(Span::raw(" ".repeat(list.marker.chars().count())));
+            }
+        }
+        prefix
+    }
+
+    /// Moves the pending spans to a new line
+    fn flush(&mut self) {
+        if self.spans.is_empty() {
+            return;
+        }
+        let mut spans = self.prefix();
+        spans.append(&mut self.spans);
+        self.lines.push(Line::from(spans));
+    }
+
+    fn blank_line(&mut self) {
+        if self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::text::Text;
+    use crate::preview::Highlighter;
+    use super::{MarkdownPreview, Renderer, is_markdown};
+
+    fn render(content: &str) -> Vec<String> {
+        Renderer::new(&Highlighter::new()).render(content).lines.iter().map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect()).collect()
+    }
+
+    #[test]
+    fn test_is_markdown() {
+        assert!(is_markdown("README.md".as_ref()));
+        assert!(is_markdown("notes.MARKDOWN".as_ref()));
+        assert!(!is_markdown("main.rs".as_ref()));
+    }
+    #[test]
+    fn test_render_blocks() {
+        assert_eq!(render("# Title\n\nSome *em* and `code`.\n"), ["Title", "", "Some em and code.", ""]);
+        // the lines of a nested list are indented by the marker of the item containing it
+        assert_eq!(render("- a\n- b\n  1. x\n  2. y\n"), ["• a", "• b", "  1. x", "  2. y", ""]);
+        assert_eq!(render("- [x] done\n- [ ] todo\n"), ["• [x] done", "• [ ] todo", ""]);
+        assert_eq!(render("> quote\n> > nested\n"), ["│ quote", "", "│ │ nested", ""]);
+        assert_eq!(render("```rust\nlet x = 1;\n```\n"), ["  let x = 1;", ""]);
+    }
+    #[test]
+    fn test_render_table_and_links() {
+        // the columns are aligned on the widest cell
+        assert_eq!(render("| h1 | header 2 |\n|----|---|\n| a | `b` |\n"), ["h1 │ header 2", "───┼─────────", "a  │ b       ", ""]);
+        assert_eq!(render("[link](http://x.y) and ![alt](img.png)\n"),
+            ["link[1] and [image: alt][2]", "", "Links:", "[1] http://x.y", "[2] img.png"]);
+    }
+    #[test]
+    fn test_preview() {
+        let content = "# Title\n\ntext\n";
+        let mut preview = MarkdownPreview::new(content, Text::raw(content), None, &Highlighter::new());
+        assert_eq!(preview.title(), " Preview - Markdown (rendered) ");
+        preview.scroll(10);
+        assert_eq!(preview.scroll, 3);
+        // switching the view goes back to the top
+        preview.cycle_view();
+        assert_eq!((preview.title().as_str(), preview.scroll), (" Preview - Markdown (raw) ", 0));
+        preview.scroll(10);
+        assert_eq!(preview.scroll, 2);
+    }
+}
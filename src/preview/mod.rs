@@ -0,0 +1,207 @@
+mod highlight;
+pub use highlight::Highlighter;
+
+pub mod encoding;
+
+mod structured;
+pub use structured::StructuredPreview;
+use structured::Format;
+
+mod table;
+pub use table::TablePreview;
+
+mod markdown;
+pub use markdown::MarkdownPreview;
+
+pub mod image;
+pub use image::ImagePreview;
+
+mod archive;
+pub use archive::ArchivePreview;
+
+use std::path::{Path, PathBuf};
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}, widgets::{Paragraph, Widget}
+};
+use ratatui::style::{Style, Color};
+use crate::file::{File, Size};
+use crate::utility::string::expand_or_truncate;
+use encoding_rs::Encoding;
+
+// Number of spaces a tabulation is expanded to in the previews
+static TAB_WIDTH: usize = 4;
+// Char size width reserved for the size column in a folder preview
+static DIRECTORY_SIZE_WIDTH: usize = 10;
+
+/// Content displayed in the preview section for the selected file
+pub enum Preview {
+    Empty,
+    Message(String),
+    Text { text: Text<'static>, encoding: Option<&'static Encoding>, scroll: usize },
+    Structured(Box<StructuredPreview>),
+    Table(Box<TablePreview>),
+    Markdown(Box<MarkdownPreview>),
+    Image(Box<ImagePreview>),
+    Archive(Box<ArchivePreview>),
+    Directory { entries: Vec<File>, total_size: u64 },
+    Link { target: PathBuf, is_dead: bool, chain: Vec<PathBuf>, canonical: Option<PathBuf> },
+}
+
+impl Preview {
+    /// Builds the preview of a decoded text content, the renderer is chosen from the path extension or from the content itself,
+    /// `truncated` tells if the content was cut by the read limit
+    pub fn text(path: &Path, content: &str, encoding: Option<&'static Encoding>, truncated: bool, highlighter: &Highlighter) -> Self {
+        let text = highlighter.highlight(path, content).unwrap_or_else(|| plain_text(content));
+        if markdown::is_markdown(path) {
+            return Preview::Markdown(Box::new(MarkdownPreview::new(content, text, encoding, highlighter)));
+        }
+        if table::is_table(path) {
+            return Preview::Table(Box::new(TablePreview::new(content, text, encoding, truncated)));
+        }
+        if let Some(format) = Format::detect(path, content) {
+            return Preview::Structured(Box::new(StructuredPreview::new(format, content, text, encoding, truncated, highlighter)));
+        }
+        Preview::Text { text, encoding, scroll: 0 }
+    }
+
+    /// Builds the preview of a folder from its entries, the total size only counts the files directly inside it
+    pub fn directory(entries: Vec<File>) -> Self {
+        let total_size = entries.iter().filter(|entry| !entry.is_folder()).map(|entry| entry.bytes()).sum();
+        Preview::Directory { entries, total_size }
+    }
+
+    /// Returns the title of the preview section
+    pub fn title(&self) -> String {
+        match self {
+            Preview::Directory { entries, total_size } => format!(" Preview - {} entries, {} ", entries.len(), Size::from(*total_size)),
+            Preview::Link { is_dead: true, .. } => " Preview - Link (Dead) ".to_string(),
+            Preview::Link { .. } => " Preview - Link ".to_string(),
+            Preview::Text { encoding: Some(encoding), .. } => format!(" Preview - {} ", encoding.name()),
+            Preview::Structured(preview) => preview.title(),
+            Preview::Table(preview) => preview.title(),
+            Preview::Markdown(preview) => preview.title(),
+            Preview::Image(preview) => preview.title(),
+            Preview::Archive(preview) => preview.title(),
+            _ => " Preview ".to_string(),
+        }
+    }
+
+    /// Scrolls the preview vertically by the given number of lines
+    pub fn scroll(&mut self, delta: isize) {
+        match self {
+            Preview::Text { text, scroll, .. } => *scroll = scroll.saturating_add_signed(delta).min(text.lines.len().saturating_sub(1)),
+            Preview::Structured(preview) => preview.scroll(delta),
+            Preview::Table(preview) => preview.scroll(delta),
+            Preview::Markdown(preview) => preview.scroll(delta),
+            Preview::Archive(preview) => preview.scroll(delta),
+            _ => {},
+        }
+    }
+
+    /// Scrolls a text preview to the scroll of the previous preview of the same file, or to its last lines when it's followed
+    pub fn resume(&mut self, previous: Option<&Preview>, follow: bool, height: usize) {
+        let Preview::Text { text, scroll, .. } = self else {
+            return;
+        };
+        *scroll = match (follow, previous) {
+            (true, _) => text.lines.len().saturating_sub(height),
+            (false, Some(Preview::Text { scroll: previous, .. })) => (*previous).min(text.lines.len().saturating_sub(1)),
+            (false, _) => *scroll,
+        };
+    }
+
+    /// Scrolls the preview horizontally when it's wider than the preview section
+    pub fn scroll_horizontally(&mut self, delta: isize) {
+        if let Preview::Table(preview) = self {
+            preview.scroll_horizontally(delta);
+        }
+    }
+
+    /// Switches to the next view of the preview when it has several ones
+    pub fn cycle_view(&mut self) {
+        match self {
+            Preview::Structured(preview) => preview.cycle_view(),
+            Preview::Table(preview) => preview.cycle_view(),
+            Preview::Markdown(preview) => preview.cycle_view(),
+            _ => {},
+        }
+    }
+
+    /// Collapses or expands the element under the cursor when the preview is a tree
+    pub fn toggle(&mut self) {
+        if let Preview::Structured(preview) = self {
+            preview.toggle();
+        }
+    }
+}
+
+impl Widget for &mut Preview {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        match self {
+            Preview::Empty => {},
+            Preview::Message(message) => {
+                Paragraph::new(message.as_str()).render(area, buf);
+            },
+            Preview::Text { text, scroll, .. } => render_lines(text, *scroll, area, buf),
+            Preview::Structured(preview) => preview.render(area, buf),
+            Preview::Table(preview) => preview.render(area, buf),
+            Preview::Markdown(preview) => preview.render(area, buf),
+            Preview::Image(preview) => preview.render(area, buf),
+            Preview::Archive(preview) => preview.render(area, buf),
+            Preview::Directory { entries, .. } => {
+                if entries.is_empty() {
+                    Paragraph::new("Empty folder").render(area, buf);
+                    return;
+                }
+                let name_width = (area.width as usize).saturating_sub(DIRECTORY_SIZE_WIDTH);
+                let visible = entries.iter().take(area.height as usize).map(|entry| {
+                    if entry.is_folder() {
+                        Line::styled(format!("{}/", entry.name()), Style::default().fg(Color::Blue))
+                    }
+                    else {
+                        Line::from(format!("{}{}", expand_or_truncate(entry.name().to_string(), name_width), entry.size()))
+                    }
+                }).collect::<Vec<Line>>();
+                Paragraph::new(visible).render(area, buf);
+            },
+            Preview::Link { target, is_dead, chain, canonical } => {
+                let label = Style::default().fg(Color::Yellow);
+                let mut lines = vec![
+                    Line::from(vec![Span::styled("Target:   ", label), Span::raw(target.to_string_lossy().to_string())]),
+                    match is_dead {
+                        true => Line::from(vec![Span::styled("Status:   ", label), Span::styled("Dead", Style::default().fg(Color::Red))]),
+                        false => Line::from(vec![Span::styled("Status:   ", label), Span::styled("Alive", Style::default().fg(Color::Green))]),
+                    },
+                    match canonical {
+                        Some(canonical) => Line::from(vec![Span::styled("Resolved: ", label), Span::raw(canonical.to_string_lossy().to_string())]),
+                        None => Line::from(vec![Span::styled("Resolved: ", label), Span::raw("-")]),
+                    },
+                    Line::default(),
+                    Line::styled("Chain:", label),
+                ];
+                // the first element of the chain is the link itself
+                lines.extend(chain.iter().enumerate().map(|(hop, path)| {
+                    let arrow = if hop == 0 { "   " } else { "-> " };
+                    Line::from(format!("{}{}", arrow, path.to_string_lossy()))
+                }));
+                Paragraph::new(lines).render(area, buf);
+            },
+        }
+    }
+}
+
+/// Builds a plain text from the content, one line per content line
+fn plain_text(content: &str) -> Text<'static> {
+    Text::from(content.lines().map(|line| Line::from(expand_tabs(line))).collect::<Vec<Line>>())
+}
+
+/// Renders the lines of the text starting from the scroll offset, only the visible lines are cloned to build the paragraph
+fn render_lines(text: &Text<'static>, scroll: usize, area: Rect, buf: &mut Buffer) {
+    let visible = text.lines.iter().skip(scroll).take(area.height as usize).cloned().collect::<Vec<Line>>();
+    Paragraph::new(visible).render(area, buf);
+}
+
+/// Replaces the tabulations by spaces, the terminal cells do not render them
+fn expand_tabs(line: &str) -> String {
+    line.replace('\t', &" ".repeat(TAB_WIDTH))
+}
@@ -0,0 +1,490 @@
+use std::path::Path;
+use encoding_rs::Encoding;
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::{Line, Span, Text}, widgets::{List, ListState, Paragraph, StatefulWidget, Widget}
+};
+use ratatui::style::{Style, Color, Modifier};
+use ratatui::layout::{Layout, Direction, Constraint};
+use serde::Deserialize;
+use crate::preview::Highlighter;
+
+// Max number of chars of a string value displayed on a tree row
+static MAX_VALUE_WIDTH: usize = 80;
+
+/// Data formats that can be previewed as a tree
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// Finds the format from the path extension, then from the content for JSON files without extension
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref() {
+            Some("json") | Some("geojson") | Some("jsonc") => Some(Format::Json),
+            Some("yaml") | Some("yml") => Some(Format::Yaml),
+            Some("toml") => Some(Format::Toml),
+            Some(_) => None,
+            None => match content.trim_start().chars().next() {
+                Some('{') | Some('[') if serde_json::from_str::<serde_json::Value>(content).is_ok() => Some(Format::Json),
+                _ => None,
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+        }
+    }
+
+    /// Parses the content into the generic data tree and pretty prints it back in the same format
+    fn parse(&self, content: &str) -> Result<(Data, String), String> {
+        match self {
+            Format::Json => {
+                let value = serde_json::from_str::<serde_json::Value>(content)
+                    .map_err(|error| format!("JSON error at line {}, column {}: {}", error.line(), error.column(), error))?;
+                let pretty = serde_json::to_string_pretty(&value).map_err(|error| error.to_string())?;
+                Ok((Data::from(value), pretty))
+            },
+            Format::Yaml => {
+                // every document of a multi documents file is kept
+                let mut documents = Vec::new();
+                for document in serde_yaml::Deserializer::from_str(content) {
+                    // the yaml errors already end with their line and column
+                    let value = serde_yaml::Value::deserialize(document).map_err(|error| format!("YAML error: {}", error))?;
+                    documents.push(value);
+                }
+                let pretty = documents.iter().map(serde_yaml::to_string).collect::<Result<Vec<String>, _>>().map_err(|error| error.to_string())?.join("---\n");
+                let data = match documents.len() {
+                    1 => Data::from(documents.remove(0)),
+                    _ => Data::Array(documents.into_iter().map(Data::from).collect()),
+                };
+                Ok((data, pretty))
+            },
+            Format::Toml => {
+                let table = toml::from_str::<toml::Table>(content).map_err(|error| match error.span() {
+                    Some(span) => {
+                        let (line, column) = line_and_column(content, span.start);
+                        format!("TOML error at line {}, column {}: {}", line, column, error.message())
+                    },
+                    None => format!("TOML error: {}", error.message()),
+                })?;
+                let pretty = toml::to_string_pretty(&table).map_err(|error| error.to_string())?;
+                Ok((Data::from(toml::Value::Table(table)), pretty))
+            },
+        }
+    }
+}
+
+/// Returns the 1 based line and column of a byte offset in the content
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|last| last.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// Generic data tree the parsed formats are converted into
+enum Data {
+    Object(Vec<(String, Data)>),
+    Array(Vec<Data>),
+    String(String),
+    Number(String),
+    Bool(bool),
+    DateTime(String),
+    Null,
+}
+
+impl From<serde_json::Value> for Data {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Object(map) => Data::Object(map.into_iter().map(|(key, value)| (key, Data::from(value))).collect()),
+            serde_json::Value::Array(values) => Data::Array(values.into_iter().map(Data::from).collect()),
+            serde_json::Value::String(string) => Data::String(string),
+            serde_json::Value::Number(number) => Data::Number(number.to_string()),
+            serde_json::Value::Bool(boolean) => Data::Bool(boolean),
+            serde_json::Value::Null => Data::Null,
+        }
+    }
+}
+
+impl From<serde_yaml::Value> for Data {
+    fn from(value: serde_yaml::Value) -> Self {
+        match value {
+            serde_yaml::Value::Mapping(mapping) => Data::Object(mapping.into_iter().map(|(key, value)| {
+                let key = match key {
+                    serde_yaml::Value::String(key) => key,
+                    key => serde_yaml::to_string(&key).map(|key| key.trim_end().to_string()).unwrap_or_default(),
+                };
+                (key, Data::from(value))
+            }).collect()),
+            serde_yaml::Value::Sequence(values) => Data::Array(values.into_iter().map(Data::from).collect()),
+            serde_yaml::Value::String(string) => Data::String(string),
+            serde_yaml::Value::Number(number) => Data::Number(number.to_string()),
+            serde_yaml::Value::Bool(boolean) => Data::Bool(boolean),
+            serde_yaml::Value::Tagged(tagged) => Data::from(tagged.value),
+            serde_yaml::Value::Null => Data::Null,
+        }
+    }
+}
+
+impl From<toml::Value> for Data {
+    fn from(value: toml::Value) -> Self {
+        match value {
+            toml::Value::Table(table) => Data::Object(table.into_iter().map(|(key, value)| (key, Data::from(value))).collect()),
+            toml::Value::Array(values) => Data::Array(values.into_iter().map(Data::from).collect()),
+            toml::Value::String(string) => Data::String(string),
+            toml::Value::Integer(integer) => Data::Number(integer.to_string()),
+            toml::Value::Float(float) => Data::Number(float.to_string()),
+            toml::Value::Boolean(boolean) => Data::Bool(boolean),
+            toml::Value::Datetime(datetime) => Data::DateTime(datetime.to_string()),
+        }
+    }
+}
+
+/// Node of the flattened tree, the nodes are stored in pre-order so the descendants of a node are the nodes until `end`
+struct TreeNode {
+    depth: usize,
+    key: String,
+    path: String,
+    value: NodeValue,
+    end: usize,
+}
+
+enum NodeValue {
+    Object(usize),
+    Array(usize),
+    String(String),
+    Number(String),
+    Bool(bool),
+    DateTime(String),
+    Null,
+}
+
+impl NodeValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            NodeValue::Object(_) => "object",
+            NodeValue::Array(_) => "array",
+            NodeValue::String(_) => "string",
+            NodeValue::Number(_) => "number",
+            NodeValue::Bool(_) => "boolean",
+            NodeValue::DateTime(_) => "datetime",
+            NodeValue::Null => "null",
+        }
+    }
+
+    fn to_span(&self) -> Span<'static> {
+        match self {
+            NodeValue::Object(length) => Span::styled(format!("{{{}}}", length), Style::default().fg(Color::DarkGray)),
+            NodeValue::Array(length) => Span::styled(format!("[{}]", length), Style::default().fg(Color::DarkGray)),
+            NodeValue::String(string) => {
+                let mut string = string.replace('\n', "\\n");
+                if string.chars().count() > MAX_VALUE_WIDTH {
+                    string = format!("{}...", string.chars().take(MAX_VALUE_WIDTH).collect::<String>());
+                }
+                Span::styled(format!("\"{}\"", string), Style::default().fg(Color::Green))
+            },
+            NodeValue::Number(number) => Span::styled(number.clone(), Style::default().fg(Color::Cyan)),
+            NodeValue::Bool(boolean) => Span::styled(boolean.to_string(), Style::default().fg(Color::Magenta)),
+            NodeValue::DateTime(datetime) => Span::styled(datetime.clone(), Style::default().fg(Color::Yellow)),
+            NodeValue::Null => Span::styled("null", Style::default().fg(Color::DarkGray)),
+        }
+    }
+}
+
+/// Flattens the data tree in pre-order, the key paths use the JSONPath notation
+fn flatten(data: Data, depth: usize, key: String, path: String, nodes: &mut Vec<TreeNode>) {
+    let index = nodes.len();
+    let value = match &data {
+        Data::Object(entries) => NodeValue::Object(entries.len()),
+        Data::Array(values) => NodeValue::Array(values.len()),
+        Data::String(string) => NodeValue::String(string.clone()),
+        Data::Number(number) => NodeValue::Number(number.clone()),
+        Data::Bool(boolean) => NodeValue::Bool(*boolean),
+        Data::DateTime(datetime) => NodeValue::DateTime(datetime.clone()),
+        Data::Null => NodeValue::Null,
+    };
+    nodes.push(TreeNode { depth, key, path: path.clone(), value, end: index + 1 });
+    match data {
+        Data::Object(entries) => {
+            for (key, value) in entries {
+                let child_path = if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                    format!("{}.{}", path, key)
+                } else {
+                    format!("{}[\"{}\"]", path, key.replace('"', "\\\""))
+                };
+                flatten(value, depth + 1, key, child_path, nodes);
+            }
+        },
+        Data::Array(values) => {
+            for (position, value) in values.into_iter().enumerate() {
+                flatten(value, depth + 1, format!("[{}]", position), format!("{}[{}]", path, position), nodes);
+            }
+        },
+        _ => {},
+    }
+    nodes[index].end = nodes.len();
+}
+
+/// Views available on a structured preview
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum View {
+    Raw,
+    Pretty,
+    Tree,
+}
+
+/// Preview of a data file, displayed as the raw text, pretty printed or as a collapsible tree
+pub struct StructuredPreview {
+    format: Format,
+    encoding: Option<&'static Encoding>,
+    raw: Text<'static>,
+    pretty: Text<'static>,
+    nodes: Vec<TreeNode>,
+    collapsed: Vec<bool>,
+    visible: Vec<usize>,
+    error: Option<String>,
+    // the content was cut by the read limit, it's only shown as text
+    truncated: bool,
+    view: View,
+    scroll: usize,
+    tree_state: ListState,
+}
+
+impl StructuredPreview {
+    pub fn new(format: Format, content: &str, raw: Text<'static>, encoding: Option<&'static Encoding>, truncated: bool, highlighter: &Highlighter) -> Self {
+        let mut preview = Self {
+            format,
+            encoding,
+            raw,
+            pretty: Text::default(),
+            nodes: Vec::new(),
+            collapsed: Vec::new(),
+            visible: Vec::new(),
+            error: None,
+            truncated,
+            view: View::Raw,
+            scroll: 0,
+            tree_state: ListState::default().with_selected(Some(0)),
+        };
+        // a cut content is invalid or only a part of the data, it's not parsed
+        if truncated {
+            return preview;
+        }
+        match format.parse(content) {
+            Ok((data, pretty)) => {
+                preview.pretty = highlighter.highlight_token(format.extension(), &pretty).unwrap_or_else(|| super::plain_text(&pretty));
+                flatten(data, 0, String::new(), "$".to_string(), &mut preview.nodes);
+                preview.collapsed = vec![false; preview.nodes.len()];
+                preview.update_visible();
+            },
+            Err(error) => preview.error = Some(error),
+        }
+        preview
+    }
+
+    pub fn title(&self) -> String {
+        let view = match self.view {
+            View::Raw => "raw",
+            View::Pretty => "pretty",
+            View::Tree => "tree",
+        };
+        match (self.encoding, &self.error) {
+            (_, Some(_)) => format!(" Preview - {} (invalid) ", self.format.name()),
+            (Some(encoding), None) if self.truncated => format!(" Preview - {} - {} (truncated) ", encoding.name(), self.format.name()),
+            (None, None) if self.truncated => format!(" Preview - {} (truncated) ", self.format.name()),
+            (Some(encoding), None) => format!(" Preview - {} - {} ({}) ", encoding.name(), self.format.name(), view),
+            (None, None) => format!(" Preview - {} ({}) ", self.format.name(), view),
+        }
+    }
+
+    /// Cycles between the raw, pretty and tree views, an invalid or truncated content can only be displayed raw
+    pub fn cycle_view(&mut self) {
+        if self.error.is_some() || self.truncated {
+            return;
+        }
+        self.view = match self.view {
+            View::Raw => View::Pretty,
+            View::Pretty => View::Tree,
+            View::Tree => View::Raw,
+        };
+        self.scroll = 0;
+    }
+
+    /// Scrolls the text views or moves the cursor of the tree view
+    pub fn scroll(&mut self, delta: isize) {
+        match self.view {
+            View::Tree => {
+                let selected = self.tree_state.selected().unwrap_or(0).saturating_add_signed(delta);
+                self.tree_state.select(Some(selected.min(self.visible.len().saturating_sub(1))));
+            },
+            View::Raw => self.scroll = self.scroll.saturating_add_signed(delta).min(self.raw.lines.len().saturating_sub(1)),
+            View::Pretty => self.scroll = self.scroll.saturating_add_signed(delta).min(self.pretty.lines.len().saturating_sub(1)),
+        }
+    }
+
+    /// Collapses or expands the node under the cursor of the tree view
+    pub fn toggle(&mut self) {
+        if self.view != View::Tree {
+            return;
+        }
+        if let Some(&index) = self.tree_state.selected().and_then(|selected| self.visible.get(selected))
+            && self.nodes[index].end > index + 1 {
+            self.collapsed[index] = !self.collapsed[index];
+            self.update_visible();
+        }
+    }
+
+    fn update_visible(&mut self) {
+        self.visible.clear();
+        let mut index = 0;
+        while index < self.nodes.len() {
+            self.visible.push(index);
+            index = if self.collapsed[index] { self.nodes[index].end } else { index + 1 };
+        }
+    }
+
+    fn tree_line(&self, index: usize) -> Line<'static> {
+        let node = &self.nodes[index];
+        let marker = match (node.end > index + 1, self.collapsed[index]) {
+            (true, true) => "▸ ",
+            (true, false) => "▾ ",
+            (false, _) => "  ",
+        };
+        let mut spans = vec![Span::raw(format!("{}{}", "  ".repeat(node.depth), marker))];
+        if !node.key.is_empty() {
+            spans.push(Span::styled(node.key.clone(), Style::default().fg(Color::Blue)));
+            spans.push(Span::raw(": "));
+        }
+        spans.push(node.value.to_span());
+        spans.push(Span::styled(format!("  {}", node.value.type_name()), Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)));
+        Line::from(spans)
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let note = match (&self.error, self.truncated) {
+            (Some(error), _) => Some(Line::styled(error.clone(), Style::default().fg(Color::Red))),
+            (None, true) => Some(Line::styled("File too big to be parsed, only its start is shown as text", Style::default().fg(Color::DarkGray))),
+            (None, false) => None,
+        };
+        if let Some(note) = note {
+            let layout = Layout::default().direction(Direction::Vertical).constraints(vec![Constraint::Length(1), Constraint::Percentage(100)]).split(area);
+            Paragraph::new(note).render(layout[0], buf);
+            super::render_lines(&self.raw, self.scroll, layout[1], buf);
+            return;
+        }
+        match self.view {
+            View::Raw => super::render_lines(&self.raw, self.scroll, area, buf),
+            View::Pretty => super::render_lines(&self.pretty, self.scroll, area, buf),
+            View::Tree => {
+                // the key path of the node under the cursor is displayed under the tree
+                let layout = Layout::default().direction(Direction::Vertical).constraints(vec![Constraint::Percentage(100), Constraint::Length(1)]).split(area);
+                let height = layout[0].height as usize;
+                let selected = self.tree_state.selected().unwrap_or(0);
+                // only the visible rows are built, the offset keeps the cursor inside the window
+                let mut offset = self.tree_state.offset();
+                if selected < offset {
+                    offset = selected;
+                }
+                else if selected >= offset + height {
+                    offset = selected + 1 - height;
+                }
+                *self.tree_state.offset_mut() = offset;
+                let end = (offset + height).min(self.visible.len());
+                let lines = self.visible[offset.min(end)..end].iter().map(|index| self.tree_line(*index)).collect::<Vec<Line>>();
+                let mut state = ListState::default().with_selected(Some(selected - offset));
+                let list = List::new(lines).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                StatefulWidget::render(list, layout[0], buf, &mut state);
+                if let Some(&index) = self.visible.get(selected) {
+                    Paragraph::new(Line::styled(self.nodes[index].path.clone(), Style::default().fg(Color::Yellow))).render(layout[1], buf);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use ratatui::text::Text;
+    use crate::preview::Highlighter;
+    use super::{Format, StructuredPreview, View};
+
+    fn preview(format: Format, content: &str, truncated: bool) -> StructuredPreview {
+        StructuredPreview::new(format, content, Text::raw(content.to_string()), None, truncated, &Highlighter::new())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Format::detect(Path::new("a.JSON"), ""), Some(Format::Json));
+        assert_eq!(Format::detect(Path::new("a.yml"), ""), Some(Format::Yaml));
+        assert_eq!(Format::detect(Path::new("Cargo.toml"), ""), Some(Format::Toml));
+        assert_eq!(Format::detect(Path::new("a.txt"), "{}"), None);
+        assert_eq!(Format::detect(Path::new("data"), " {\"a\": 1}"), Some(Format::Json));
+        assert_eq!(Format::detect(Path::new("data"), "{not json"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let (_, pretty) = Format::Json.parse("{\"b\":1,\"a\":[true,null]}").unwrap();
+        // the keys keep the order of the file
+        assert_eq!(pretty, "{\n  \"b\": 1,\n  \"a\": [\n    true,\n    null\n  ]\n}");
+        assert!(Format::Json.parse("{\n\"a\": }").err().unwrap().starts_with("JSON error at line 2, column 6"));
+        assert!(Format::Toml.parse("a = 1\nb = ").err().unwrap().starts_with("TOML error at line 2"));
+        let (_, pretty) = Format::Yaml.parse("a: 1\n---\nb: 2\n").unwrap();
+        assert_eq!(pretty, "a: 1\n---\nb: 2\n");
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut preview = preview(Format::Json, "{\"a\":{\"b c\":1},\"d\":[2,3]}", false);
+        let paths = preview.nodes.iter().map(|node| node.path.as_str()).collect::<Vec<&str>>();
+        assert_eq!(paths, vec!["$", "$.a", "$.a[\"b c\"]", "$.d", "$.d[0]", "$.d[1]"]);
+        assert_eq!(preview.nodes[0].end, 6);
+        assert_eq!(preview.nodes[1].end, 3);
+
+        // the toggle only applies to the tree view, on a node with children
+        preview.cycle_view();
+        preview.cycle_view();
+        assert_eq!(preview.view, View::Tree);
+        preview.scroll(1);
+        preview.toggle();
+        assert_eq!(preview.visible, vec![0, 1, 3, 4, 5]);
+        preview.scroll(2);
+        preview.toggle();
+        assert_eq!(preview.visible, vec![0, 1, 3, 4, 5]);
+        preview.scroll(-2);
+        preview.toggle();
+        assert_eq!(preview.visible.len(), 6);
+    }
+
+    #[test]
+    fn test_invalid_and_truncated() {
+        let mut invalid = preview(Format::Json, "{\"a\": ", false);
+        assert!(invalid.error.is_some());
+        assert_eq!(invalid.title(), " Preview - JSON (invalid) ");
+        invalid.cycle_view();
+        assert_eq!(invalid.view, View::Raw);
+
+        // a cut content is shown as text without an error
+        let mut truncated = preview(Format::Json, "{\"a\": ", true);
+        assert!(truncated.error.is_none());
+        assert_eq!(truncated.title(), " Preview - JSON (truncated) ");
+        truncated.cycle_view();
+        assert_eq!(truncated.view, View::Raw);
+    }
+}
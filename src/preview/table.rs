@@ -0,0 +1,161 @@
+use std::path::Path;
+use encoding_rs::Encoding;
+use ratatui::{
+    buffer::Buffer, layout::Rect, text::Text, widgets::{Cell, Row, Table, Widget}
+};
+use ratatui::style::{Style, Color, Modifier};
+use ratatui::layout::Constraint;
+
+// Delimiters tried when sniffing the delimiter of a file
+static CANDIDATE_DELIMITERS: &[u8] = b",;\t|";
+// Number of lines read to sniff the delimiter
+static SNIFF_LINES: usize = 20;
+// Max char size width of a column, longer cells are truncated
+static MAX_COLUMN_WIDTH: usize = 40;
+// Char size width between two columns
+static COLUMN_SPACING: u16 = 2;
+
+/// Returns true if the path extension is one of a delimiter separated values file
+pub fn is_table(path: &Path) -> bool {
+    matches!(path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref(), Some("csv") | Some("tsv") | Some("psv"))
+}
+
+/// Sniffs the delimiter of the content, the best delimiter appears the same non zero number of times on most of the first lines,
+/// the occurrences inside quoted fields are ignored
+pub fn sniff_delimiter(content: &str) -> u8 {
+    let lines = content.lines().filter(|line| !line.trim().is_empty()).take(SNIFF_LINES).collect::<Vec<&str>>();
+    let mut best = (b',', 0);
+    for &delimiter in CANDIDATE_DELIMITERS {
+        let counts = lines.iter().map(|line| count_unquoted(line, delimiter)).collect::<Vec<usize>>();
+        let first = match counts.first() {
+            Some(&first) if first > 0 => first,
+            _ => continue,
+        };
+        let consistent = counts.iter().filter(|&&count| count == first).count();
+        if consistent > best.1 {
+            best = (delimiter, consistent);
+        }
+    }
+    best.0
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            quoted = !quoted;
+        }
+        else if byte == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Preview of a delimiter separated values file displayed as a table with a header row
+pub struct TablePreview {
+    encoding: Option<&'static Encoding>,
+    delimiter: u8,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    widths: Vec<usize>,
+    raw: Text<'static>,
+    show_raw: bool,
+    scroll: usize,
+    column_offset: usize,
+}
+
+impl TablePreview {
+    pub fn new(content: &str, raw: Text<'static>, encoding: Option<&'static Encoding>, truncated: bool) -> Self {
+        let delimiter = sniff_delimiter(content);
+        let mut records = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content.as_bytes())
+            .into_records()
+            .filter_map(|record| record.ok())
+            .map(|record| record.iter().map(|field| field.replace(['\n', '\r'], " ")).collect::<Vec<String>>())
+            .collect::<Vec<Vec<String>>>();
+        // the last record of a truncated content is most likely incomplete
+        if truncated {
+            records.pop();
+        }
+        let header = if records.is_empty() { Vec::new() } else { records.remove(0) };
+        let columns = records.iter().map(|record| record.len()).chain(std::iter::once(header.len())).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for record in std::iter::once(&header).chain(records.iter()) {
+            for (column, field) in record.iter().enumerate() {
+                widths[column] = widths[column].max(field.chars().count().min(MAX_COLUMN_WIDTH));
+            }
+        }
+        Self { encoding, delimiter, header, rows: records, widths, raw, show_raw: false, scroll: 0, column_offset: 0 }
+    }
+
+    pub fn title(&self) -> String {
+        let delimiter = match self.delimiter {
+            b'\t' => "TSV".to_string(),
+            b',' => "CSV".to_string(),
+            delimiter => format!("'{}' separated", delimiter as char),
+        };
+        let view = if self.show_raw { "raw" } else { "table" };
+        match self.encoding {
+            Some(encoding) => format!(" Preview - {} - {} {} rows x {} columns ({}) ", encoding.name(), delimiter, self.rows.len(), self.widths.len(), view),
+            None => format!(" Preview - {} {} rows x {} columns ({}) ", delimiter, self.rows.len(), self.widths.len(), view),
+        }
+    }
+
+    pub fn cycle_view(&mut self) {
+        self.show_raw = !self.show_raw;
+        self.scroll = 0;
+    }
+
+    pub fn scroll(&mut self, delta: isize) {
+        let length = if self.show_raw { self.raw.lines.len() } else { self.rows.len() };
+        self.scroll = self.scroll.saturating_add_signed(delta).min(length.saturating_sub(1));
+    }
+
+    /// Scrolls the table by whole columns
+    pub fn scroll_horizontally(&mut self, delta: isize) {
+        self.column_offset = self.column_offset.saturating_add_signed(delta).min(self.widths.len().saturating_sub(1));
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        if self.show_raw {
+            super::render_lines(&self.raw, self.scroll, area, buf);
+            return;
+        }
+        // fit as many columns as possible in the area starting from the column offset, the last one takes the remaining space
+        let mut constraints = Vec::new();
+        let mut used = 0;
+        for width in self.widths.iter().skip(self.column_offset) {
+            if used >= area.width {
+                break;
+            }
+            constraints.push(Constraint::Length(*width as u16));
+            used = used.saturating_add(*width as u16 + COLUMN_SPACING);
+        }
+        if let Some(last) = constraints.last_mut() {
+            *last = Constraint::Fill(1);
+        }
+        let columns = self.column_offset..self.column_offset + constraints.len();
+        let to_row = |record: &Vec<String>| Row::new(columns.clone().map(|column| Cell::from(record.get(column).cloned().unwrap_or_default())));
+        let header = to_row(&self.header).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+        let rows = self.rows.iter().skip(self.scroll).take(area.height.saturating_sub(1) as usize).map(to_row).collect::<Vec<Row>>();
+        Table::new(rows, constraints).header(header).column_spacing(COLUMN_SPACING).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::sniff_delimiter;
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(sniff_delimiter("a,b,c\n1,2,3\n"), b',');
+        assert_eq!(sniff_delimiter("a;b;c\n\"1,5\";2;3\n"), b';');
+        assert_eq!(sniff_delimiter("a\tb\n1\t2\n"), b'\t');
+        assert_eq!(sniff_delimiter("name|comment\nx|\"a, b, c\"\n"), b'|');
+        assert_eq!(sniff_delimiter("single column\n"), b',');
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::os::unix::fs::symlink;
+use crate::archive::{self, ConflictPolicy, Progress};
+
+/// Operation applied to the files sent to another folder
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+/// Result of a copy or a move
+#[derive(Debug, Default)]
+pub struct TransferSummary {
+    pub transferred: usize,
+    pub skipped: usize,
+}
+
+/// Copies or moves the sources into the destination folder, each source keeps its name unless the policy renames it.
+/// A move is a rename when the destination is on the same filesystem, a copy followed by the removal of the source otherwise
+pub fn transfer(sources: &[PathBuf], destination: &Path, mode: TransferMode, policy: ConflictPolicy, progress: &mut dyn Progress) -> io::Result<TransferSummary> {
+    check_destination(sources, destination)?;
+    let sizes = sources.iter()
+        .map(|source| archive::collect_sources(std::slice::from_ref(source)).map(|entries| archive::sources_size(&entries)))
+        .collect::<io::Result<Vec<u64>>>()?;
+    progress.set_total(sizes.iter().sum());
+    let mut summary = TransferSummary::default();
+    for (source, size) in sources.iter().zip(sizes) {
+        let target = match source.file_name().and_then(|name| policy.resolve(&destination.join(name))) {
+            // a file sent to its own folder is left untouched
+            Some(target) if target != *source => target,
+            _ => {
+                summary.skipped += 1;
+                progress.advance(size)?;
+                continue;
+            },
+        };
+        match mode {
+            TransferMode::Copy => copy_tree(source, &target, progress)?,
+            TransferMode::Move => move_tree(source, &target, size, progress)?,
+        }
+        summary.transferred += 1;
+    }
+    Ok(summary)
+}
+
+/// Rejects a destination that is not a local folder or that is inside one of the sources
+fn check_destination(sources: &[PathBuf], destination: &Path) -> io::Result<()> {
+    if archive::split_virtual_path(destination).is_some() || sources.iter().any(|source| archive::split_virtual_path(source).is_some()) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "Files can't be sent to or from an archive"));
+    }
+    let destination = fs::canonicalize(destination)?;
+    if !destination.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a folder", destination.display())));
+    }
+    for source in sources {
+        if fs::canonicalize(source).is_ok_and(|source| destination.starts_with(source)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} can't be sent into itself", source.display())));
+        }
+    }
+    Ok(())
+}
+
+/// Removes the destination if it's a link, so an existing link is replaced rather than followed when the destination is written
+pub fn replace_destination(target: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(target).is_ok_and(|metadata| metadata.is_symlink()) {
+        fs::remove_file(target)?;
+    }
+    Ok(())
+}
+
+/// Copies the file, link or folder with its permissions and modified time, the existing files of the target are overwritten
+fn copy_tree(source: &Path, target: &Path, progress: &mut dyn Progress) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(source)?;
+    if metadata.is_symlink() {
+        if fs::symlink_metadata(target).is_ok() {
+            fs::remove_file(target)?;
+        }
+        return symlink(fs::read_link(source)?, target);
+    }
+    replace_destination(target)?;
+    if metadata.is_dir() {
+        if !target.is_dir() {
+            fs::create_dir(target)?;
+        }
+        for child in fs::read_dir(source)? {
+            let child = child?;
+            copy_tree(&child.path(), &target.join(child.file_name()), progress)?;
+        }
+        return fs::set_permissions(target, metadata.permissions());
+    }
+    let mut reader = fs::File::open(source)?;
+    let mut writer = fs::File::create(target)?;
+    archive::copy(&mut reader, &mut writer, progress)?;
+    writer.set_permissions(metadata.permissions())?;
+    if let Ok(modified) = metadata.modified() {
+        writer.set_modified(modified)?;
+    }
+    Ok(())
+}
+
+/// Renames the source, falls back to a copy when the target is on another filesystem or is a folder to merge into
+fn move_tree(source: &Path, target: &Path, size: u64, progress: &mut dyn Progress) -> io::Result<()> {
+    let merge = target.is_dir() && !fs::symlink_metadata(target)?.is_symlink();
+    if !merge {
+        match fs::rename(source, target) {
+            Ok(()) => return progress.advance(size),
+            Err(error) if error.kind() == io::ErrorKind::CrossesDevices => {},
+            Err(error) => return Err(error),
+        }
+    }
+    copy_tree(source, target, progress)?;
+    if fs::symlink_metadata(source)?.is_dir() {
+        fs::remove_dir_all(source)
+    }
+    else {
+        fs::remove_file(source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::archive::{ConflictPolicy, NoProgress};
+    use crate::utility::temp::TempDir;
+    use super::{transfer, TransferMode};
+
+    #[test]
+    fn test_transfer() {
+        let root = TempDir::new("transfer");
+        let (source, destination) = (root.join("source"), root.join("destination"));
+        fs::create_dir_all(source.join("folder")).unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        fs::write(source.join("folder/a.txt"), "a").unwrap();
+        fs::write(source.join("b.txt"), "b").unwrap();
+        let sources = vec![source.join("folder"), source.join("b.txt")];
+
+        let summary = transfer(&sources, &destination, TransferMode::Copy, ConflictPolicy::Skip, &mut NoProgress).unwrap();
+        assert_eq!((summary.transferred, summary.skipped), (2, 0));
+        assert_eq!(fs::read_to_string(destination.join("folder/a.txt")).unwrap(), "a");
+        // the second copy conflicts with the first one
+        let summary = transfer(&sources, &destination, TransferMode::Copy, ConflictPolicy::Rename, &mut NoProgress).unwrap();
+        assert_eq!(summary.transferred, 2);
+        assert!(destination.join("b (1).txt").is_file());
+
+        let summary = transfer(&[source.join("b.txt")], &destination, TransferMode::Move, ConflictPolicy::Overwrite, &mut NoProgress).unwrap();
+        assert_eq!(summary.transferred, 1);
+        assert!(!source.join("b.txt").exists());
+        // an overwritten link is replaced, the file it points to is left alone
+        fs::write(root.join("outside.txt"), "outside").unwrap();
+        std::os::unix::fs::symlink(root.join("outside.txt"), destination.join("c.txt")).unwrap();
+        fs::write(source.join("c.txt"), "c").unwrap();
+        transfer(&[source.join("c.txt")], &destination, TransferMode::Copy, ConflictPolicy::Overwrite, &mut NoProgress).unwrap();
+        assert!(!fs::symlink_metadata(destination.join("c.txt")).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(root.join("outside.txt")).unwrap(), "outside");
+        // a folder can't be sent into itself
+        assert!(transfer(std::slice::from_ref(&source), &source.join("folder"), TransferMode::Copy, ConflictPolicy::Skip, &mut NoProgress).is_err());
+        assert!(transfer(&[PathBuf::from("/tmp/x.zip!/a")], &destination, TransferMode::Copy, ConflictPolicy::Skip, &mut NoProgress).is_err());
+    }
+}
@@ -12,6 +12,7 @@ pub mod string {
         string
     }
     
+    #[allow(dead_code)]
     pub fn center(text: String, max_width: usize) -> String {
         let padding = " ".repeat((max_width.saturating_sub(text.chars().count()))/2);
         format!("{}{}{}", padding, text, padding)
@@ -20,6 +21,7 @@ pub mod string {
 }
 
 pub mod float {
+    #[allow(dead_code)]
     pub fn truncate(float: f32, precision: usize) -> f32 {
         if precision == 0 {
             return float.trunc();
@@ -29,10 +31,187 @@ pub mod float {
     }
 }
 
+pub mod fuzzy {
+    // Score of each matched character
+    static MATCH_SCORE: i64 = 16;
+    // Bonus of a character matched right after the previous one
+    static CONSECUTIVE_BONUS: i64 = 12;
+    // Bonus of a character matched at the start of a word (after a separator or a case change)
+    static WORD_START_BONUS: i64 = 10;
+    // Penalty of each skipped character between two matched ones
+    static GAP_PENALTY: i64 = 1;
+
+    /// Scores the candidate against the pattern, the pattern characters must appear in order in the candidate (case insensitive),
+    /// returns None if they don't, the higher the score the better the match
+    pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+        let mut pattern = pattern.chars().filter(|character| !character.is_whitespace()).flat_map(char::to_lowercase).peekable();
+        let mut score = 0;
+        let mut previous: Option<char> = None;
+        let mut last_match: Option<usize> = None;
+        for (index, character) in candidate.chars().enumerate() {
+            let expected = match pattern.peek() {
+                Some(expected) => *expected,
+                None => break,
+            };
+            if character.to_lowercase().eq(std::iter::once(expected)) {
+                score += MATCH_SCORE;
+                match last_match {
+                    Some(last) if last + 1 == index => score += CONSECUTIVE_BONUS,
+                    Some(last) => score -= (index - last - 1) as i64 * GAP_PENALTY,
+                    None => {},
+                }
+                let word_start = match previous {
+                    None => true,
+                    Some(previous) => matches!(previous, '/' | '_' | '-' | '.' | ' ') || (previous.is_lowercase() && character.is_uppercase()),
+                };
+                if word_start {
+                    score += WORD_START_BONUS;
+                }
+                last_match = Some(index);
+                pattern.next();
+            }
+            previous = Some(character);
+        }
+        match pattern.peek() {
+            Some(_) => None,
+            None => Some(score),
+        }
+    }
+}
+
+pub mod path {
+    use std::env;
+    use std::path::{Component, Path, PathBuf};
+
+    // Name of the folder holding the application files in the XDG folders
+    static APPLICATION_FOLDER: &str = "file_manager_tui";
+
+    /// Returns the folder where the application stores its data, `$XDG_DATA_HOME` or `~/.local/share` by default
+    pub fn data_dir() -> Option<PathBuf> {
+        xdg_dir("XDG_DATA_HOME", ".local/share").map(|folder| folder.join(APPLICATION_FOLDER))
+    }
+
+    /// Returns the user configuration folder, `$XDG_CONFIG_HOME` or `~/.config` by default
+    pub fn config_dir() -> Option<PathBuf> {
+        xdg_dir("XDG_CONFIG_HOME", ".config")
+    }
+
+    pub fn home_dir() -> Option<PathBuf> {
+        env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+    }
+
+    /// Expands a typed path: `~` is the home folder, `$VAR` and `${VAR}` are environment variables and a relative path is relative to
+    /// the current folder, the `.` and `..` components are then resolved without following the links
+    pub fn expand(input: &str, current: &Path) -> Result<PathBuf, String> {
+        let expanded = expand_variables(input)?;
+        let path = match expanded.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                let home = home_dir().ok_or(String::from("No home folder"))?;
+                home.join(rest.trim_start_matches('/'))
+            },
+            _ => current.join(expanded),
+        };
+        Ok(normalize(&path))
+    }
+
+    /// Resolves the `.` and `..` components of the path, `..` at the root stays at the root
+    pub fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {},
+                Component::ParentDir => {
+                    normalized.pop();
+                },
+                _ => normalized.push(component),
+            }
+        }
+        normalized
+    }
+
+    /// Replaces the `$VAR` and `${VAR}` with the variable values, a `$` not followed by a name is kept
+    fn expand_variables(input: &str) -> Result<String, String> {
+        let mut expanded = String::with_capacity(input.len());
+        let mut characters = input.chars().peekable();
+        while let Some(character) = characters.next() {
+            if character != '$' {
+                expanded.push(character);
+                continue;
+            }
+            let braced = characters.next_if_eq(&'{').is_some();
+            let mut name = String::new();
+            while let Some(character) = characters.next_if(|character| character.is_ascii_alphanumeric() || *character == '_') {
+                name.push(character);
+            }
+            if braced && characters.next_if_eq(&'}').is_none() {
+                return Err(format!("Unclosed variable ${{{}", name));
+            }
+            if name.is_empty() {
+                expanded.push_str(if braced { "${}" } else { "$" });
+                continue;
+            }
+            let value = env::var_os(&name).ok_or(format!("Undefined variable ${}", name))?;
+            expanded.push_str(&value.to_string_lossy());
+        }
+        Ok(expanded)
+    }
+
+    /// The XDG variables are ignored when they are not absolute paths as the specification requires
+    fn xdg_dir(variable: &str, default: &str) -> Option<PathBuf> {
+        match env::var_os(variable).map(PathBuf::from) {
+            Some(folder) if folder.is_absolute() => Some(folder),
+            _ => home_dir().map(|home| home.join(default)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod temp {
+    use std::fs;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+
+    /// Folder of a test in the temporary folder, removed when dropped even if the test fails
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        /// Creates an empty folder named after the test and the process, the remains of a killed run are removed first
+        pub fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("file_manager_tui_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::string::{expand_or_truncate, center};
     use super::float::truncate;
+    use super::fuzzy::score;
+    use super::path::{expand, home_dir};
+    use std::path::{Path, PathBuf};
     #[test]
     fn test_expand_or_truncate() {
         assert_eq!(expand_or_truncate("Hello".to_string(), 10), "Hello     ".to_string());
@@ -54,4 +233,28 @@ mod test {
         assert_eq!(truncate(1.23456789, 0), 1.0);
         assert_eq!(truncate(1.23456789, 3), 1.234);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(score("", "anything"), Some(0));
+        assert_eq!(score("xyz", "abc"), None);
+        assert_eq!(score("ba", "abc"), None);
+        assert!(score("doc", "Documents").is_some());
+        // consecutive and word start matches rank first
+        assert!(score("proj", "/home/me/projects").unwrap() > score("proj", "/home/me/p_r_o_j").unwrap());
+        assert!(score("fm", "file_manager").unwrap() > score("fm", "information").unwrap());
+    }
+    #[test]
+    fn test_expand_path() {
+        let current = Path::new("/home/me/projects");
+        assert_eq!(expand("src/../docs/./a", current), Ok(PathBuf::from("/home/me/projects/docs/a")));
+        assert_eq!(expand("/etc/../../usr", current), Ok(PathBuf::from("/usr")));
+        assert_eq!(expand("", current), Ok(PathBuf::from("/home/me/projects")));
+        assert_eq!(expand("$", current), Ok(PathBuf::from("/home/me/projects/$")));
+        assert!(expand("$FILE_MANAGER_TUI_UNDEFINED/a", current).is_err());
+        assert!(expand("${HOME", current).is_err());
+        if let Some(home) = home_dir() {
+            assert_eq!(expand("~/a", current), Ok(home.join("a")));
+            assert_eq!(expand("${HOME}/a", current), Ok(home.join("a")));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::archive::Cancelled;
+use crate::file::File;
+
+// Number of files gathered before a batch is handed over
+static BATCH_SIZE: usize = 512;
+// Max time a found file waits before its batch is handed over, so the first files show up at once
+static BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// Walks the folder depth first up to the depth and hands the files under it to the sink by batches, named by their path relative to the folder.
+/// The files of a folder come before its subfolders, links are listed but not followed and the unreadable subfolders are skipped,
+/// returns the number of files found
+pub fn walk(root: &Path, depth: usize, show_hidden: bool, cancel: &dyn Fn() -> bool, sink: &mut dyn FnMut(Vec<File>)) -> io::Result<usize> {
+    let mut walker = Walker { root, show_hidden, cancel, sink, pending: Vec::new(), handed: Instant::now(), found: 0 };
+    walker.walk_folder(Path::new(""), depth)?;
+    let pending = std::mem::take(&mut walker.pending);
+    (walker.sink)(pending);
+    Ok(walker.found)
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    show_hidden: bool,
+    cancel: &'a dyn Fn() -> bool,
+    sink: &'a mut dyn FnMut(Vec<File>),
+    pending: Vec<File>,
+    // time the last batch was handed over
+    handed: Instant,
+    found: usize,
+}
+
+impl Walker<'_> {
+    fn walk_folder(&mut self, relative: &Path, depth: usize) -> io::Result<()> {
+        if (self.cancel)() {
+            return Err(io::Error::other(Cancelled));
+        }
+        let entries = match fs::read_dir(self.root.join(relative)) {
+            Ok(entries) => entries,
+            // only the walked folder itself has to be readable
+            Err(_) if !relative.as_os_str().is_empty() => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        let mut entries = entries.filter_map(Result::ok)
+            .filter_map(|entry| File::try_from(entry).ok())
+            .filter(|file| self.show_hidden || !file.is_hidden())
+            .collect::<Vec<File>>();
+        entries.sort_by_cached_key(|file| file.name().to_lowercase());
+        let (folders, files): (Vec<File>, Vec<File>) = entries.into_iter().partition(File::is_folder);
+        self.found += files.len();
+        self.pending.extend(files.into_iter().map(|file| {
+            let name = relative.join(file.name()).to_string_lossy().to_string();
+            file.with_name(name)
+        }));
+        if self.pending.len() >= BATCH_SIZE || self.handed.elapsed() >= BATCH_DELAY {
+            let pending = std::mem::take(&mut self.pending);
+            (self.sink)(pending);
+            self.handed = Instant::now();
+        }
+        if depth > 1 {
+            for folder in folders {
+                self.walk_folder(&relative.join(folder.name()), depth - 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use crate::file::File;
+    use crate::utility::temp::TempDir;
+    use super::walk;
+
+    #[test]
+    fn test_walk() {
+        let root = TempDir::new("walk");
+        fs::create_dir_all(root.join("b/c")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        for file in ["z.txt", "b/a.txt", "b/c/deep.txt", ".hidden/h.txt"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let mut files = Vec::new();
+        let found = walk(&root, 3, false, &|| false, &mut |batch: Vec<File>| files.extend(batch)).unwrap();
+        assert_eq!(found, 3);
+        assert_eq!(files.iter().map(File::name).collect::<Vec<&str>>(), vec!["z.txt", "b/a.txt", "b/c/deep.txt"]);
+
+        let mut files = Vec::new();
+        walk(&root, 2, true, &|| false, &mut |batch: Vec<File>| files.extend(batch)).unwrap();
+        assert_eq!(files.iter().map(File::name).collect::<Vec<&str>>(), vec!["z.txt", ".hidden/h.txt", "b/a.txt"]);
+        assert!(walk(&root, 2, true, &|| true, &mut |_| {}).is_err());
+    }
+}
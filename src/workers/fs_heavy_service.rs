@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::archive::{self, ArchiveKind, Cancelled, ConflictPolicy, Progress};
+use crate::preview::{ArchivePreview, Preview};
+use crate::transfer::{self, TransferMode};
+use crate::file::File;
+use crate::walk;
+use crate::disk_usage::{self, DiskUsage};
+use crate::workers::ListingOptions;
+use crate::workers::ArchiveSource;
+
+/// Service running the long filesystem operations, kept apart from the light service so they never delay a folder load
+pub struct FsHeavyService {}
+
+impl FsHeavyService {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Lists the entries of the archive to preview them
+    pub fn list_archive(&self, path: &Path) -> Result<Preview, HeavyServiceError> {
+        let kind = ArchiveKind::detect(path).ok_or(HeavyServiceError::ListFailed("Unsupported archive format".to_string()))?;
+        let compressed_size = fs::metadata(path)?.len();
+        let entries = archive::list(path, kind)?;
+        Ok(Preview::Archive(Box::new(ArchivePreview::new(kind, &entries, compressed_size))))
+    }
+
+    /// Lists the entries of the archive to browse it as a folder
+    pub fn index_archive(&self, path: &Path) -> Result<ArchiveSource, HeavyServiceError> {
+        Ok(ArchiveSource::open(path)?)
+    }
+
+    /// Extracts the archive after checking all its entries, returns a summary of the extraction
+    pub fn extract(&self, job: &ExtractJob, progress: &mut dyn Progress) -> Result<String, HeavyServiceError> {
+        let kind = ArchiveKind::detect(&job.archive).ok_or(HeavyServiceError::JobFailed("Unsupported archive format".to_string()))?;
+        progress.set_total(archive::check_entries(&job.archive, kind).map_err(job_error)?);
+        let summary = archive::extract(&job.archive, kind, &job.destination, job.policy, progress).map_err(job_error)?;
+        match summary.skipped {
+            0 => Ok(format!("Extracted {} files into {}", summary.extracted, job.destination.display())),
+            skipped => Ok(format!("Extracted {} files into {} ({} skipped)", summary.extracted, job.destination.display(), skipped)),
+        }
+    }
+
+    /// Creates an archive from the sources, a partially written archive is removed if the job fails
+    pub fn compress(&self, job: &CompressJob, progress: &mut dyn Progress) -> Result<String, HeavyServiceError> {
+        let destination = job.policy.resolve(&job.destination)
+            .ok_or(HeavyServiceError::JobFailed(format!("{} already exists", job.destination.display())))?;
+        let entries = archive::collect_sources(&job.sources).map_err(job_error)?;
+        progress.set_total(archive::sources_size(&entries));
+        if let Err(error) = archive::create(&destination, job.kind, &entries, progress) {
+            let _ = fs::remove_file(&destination);
+            return Err(job_error(error));
+        }
+        Ok(format!("Created {} ({} entries)", destination.display(), entries.len()))
+    }
+
+    /// Lists the files under the folder by batches as they are found, returns the number of files found
+    pub fn walk(&self, job: &WalkJob, cancel: &dyn Fn() -> bool, sink: &mut dyn FnMut(Vec<File>)) -> Result<usize, HeavyServiceError> {
+        if archive::split_virtual_path(&job.path).is_some() {
+            return Err(HeavyServiceError::JobFailed("Archives can't be listed recursively".to_string()));
+        }
+        walk::walk(&job.path, job.depth, job.options.show_hidden, cancel, sink).map_err(job_error)
+    }
+
+    /// Measures the apparent size and the disk usage of the folder and everything under it
+    pub fn measure(&self, path: &Path, cross_filesystems: bool, cancel: &dyn Fn() -> bool) -> Result<DiskUsage, HeavyServiceError> {
+        if archive::split_virtual_path(path).is_some() {
+            return Err(HeavyServiceError::JobFailed("Folders inside an archive can't be measured".to_string()));
+        }
+        disk_usage::measure(path, cross_filesystems, cancel).map_err(job_error)
+    }
+
+    /// Copies or moves the sources into the destination folder, returns a summary of the transfer
+    pub fn transfer(&self, job: &TransferJob, progress: &mut dyn Progress) -> Result<String, HeavyServiceError> {
+        let summary = transfer::transfer(&job.sources, &job.destination, job.mode, job.policy, progress).map_err(job_error)?;
+        let verb = match job.mode {
+            TransferMode::Copy => "Copied",
+            TransferMode::Move => "Moved",
+        };
+        match summary.skipped {
+            0 => Ok(format!("{} {} items to {}", verb, summary.transferred, job.destination.display())),
+            skipped => Ok(format!("{} {} items to {} ({} skipped)", verb, summary.transferred, job.destination.display(), skipped)),
+        }
+    }
+}
+
+/// Recursive listing of the files under a folder
+pub struct WalkJob {
+    pub path: PathBuf,
+    // 1 lists the files of the folder only
+    pub depth: usize,
+    pub options: ListingOptions,
+}
+
+/// Measure of the total size of folders, one after the other
+pub struct MeasureJob {
+    pub paths: Vec<PathBuf>,
+    // the folders mounted under the measured ones are counted too
+    pub cross_filesystems: bool,
+}
+
+/// Extraction of an archive into a folder
+pub struct ExtractJob {
+    pub archive: PathBuf,
+    pub destination: PathBuf,
+    pub policy: ConflictPolicy,
+}
+
+/// Creation of an archive from files and folders
+pub struct CompressJob {
+    pub sources: Vec<PathBuf>,
+    pub destination: PathBuf,
+    pub kind: ArchiveKind,
+    pub policy: ConflictPolicy,
+}
+
+/// Copy or move of files and folders into another folder
+pub struct TransferJob {
+    pub sources: Vec<PathBuf>,
+    pub destination: PathBuf,
+    pub mode: TransferMode,
+    pub policy: ConflictPolicy,
+}
+
+/// Converts the error of a job, a cancellation requested through the progress is reported as such
+fn job_error(error: std::io::Error) -> HeavyServiceError {
+    if error.get_ref().is_some_and(|inner| inner.is::<Cancelled>()) {
+        HeavyServiceError::Cancelled
+    }
+    else {
+        HeavyServiceError::JobFailed(error.to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HeavyServiceError {
+    #[error("{0}")]
+    ListFailed(String),
+    #[error("{0}")]
+    JobFailed(String),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl From<std::io::Error> for HeavyServiceError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => HeavyServiceError::ListFailed(format!("Path not found ({})", error)),
+            std::io::ErrorKind::PermissionDenied => HeavyServiceError::ListFailed(format!("Permission denied ({})", error)),
+            _ => HeavyServiceError::ListFailed(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::thread;
+use std::io;
+use thiserror::Error;
+use crate::workers::HeavyServiceError;
+use crate::workers::FsHeavyService;
+use crate::workers::{ExtractJob, CompressJob, TransferJob, WalkJob, MeasureJob};
+use crate::workers::ArchiveSource;
+use crate::archive::{Cancelled, Progress};
+use crate::preview::Preview;
+use crate::file::File;
+use crate::disk_usage::DiskUsage;
+use std::sync::mpsc::SendError;
+
+pub struct FsHeavyWorker {
+    input_channel: mpsc::Receiver<HeavyWorkerMessage>,
+    output_channel: mpsc::Sender<Result<HeavyWorkerResponse, HeavyWorkerError>>,
+    service: FsHeavyService,
+    // message received while skipping the outdated listings, handled before the next one in the channel
+    pending: Option<HeavyWorkerMessage>,
+}
+
+impl FsHeavyWorker {
+    pub fn new(input_channel: mpsc::Receiver<HeavyWorkerMessage>, output_channel: mpsc::Sender<Result<HeavyWorkerResponse, HeavyWorkerError>>) -> Self {
+        Self { input_channel, output_channel, service: FsHeavyService::new(), pending: None }
+    }
+
+    pub fn run(&mut self) -> Result<(), HeavyWorkerError> {
+        loop {
+            let mut message = match self.pending.take().map_or_else(|| self.input_channel.recv(), Ok) {
+                Ok(message) => message,
+                Err(_) => return Err(HeavyWorkerError::ReceiveMessageFailed),
+            };
+            // a listing is only useful for the latest selection, the ones queued right behind a newer listing are skipped
+            while let HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::ListArchive(_)) = message {
+                match self.input_channel.try_recv() {
+                    Ok(next @ HeavyWorkerMessage::WorkerAction(HeavyWorkerAction::ListArchive(_))) => message = next,
+                    Ok(next) => {
+                        self.pending = Some(next);
+                        break;
+                    },
+                    Err(_) => break,
+                }
+            }
+            match message {
+                HeavyWorkerMessage::Shutdown => break Ok(()),
+                HeavyWorkerMessage::WorkerAction(action) => self.handle_action(action)?,
+            }
+        }
+    }
+}
+
+impl FsHeavyWorker {
+    fn handle_action(&mut self, action: HeavyWorkerAction) -> Result<(), HeavyWorkerError> {
+        match action {
+            HeavyWorkerAction::ListArchive(path) => {
+                self.list_archive(path)
+            },
+            HeavyWorkerAction::IndexArchive(path) => {
+                self.index_archive(path)
+            },
+            HeavyWorkerAction::Extract{id, job, cancel} => {
+                self.spawn_job(id, cancel, move |service, progress| service.extract(&job, progress));
+                Ok(())
+            },
+            HeavyWorkerAction::Compress{id, job, cancel} => {
+                self.spawn_job(id, cancel, move |service, progress| service.compress(&job, progress));
+                Ok(())
+            },
+            HeavyWorkerAction::Transfer{id, job, cancel} => {
+                self.spawn_job(id, cancel, move |service, progress| service.transfer(&job, progress));
+                Ok(())
+            },
+            HeavyWorkerAction::Walk{id, job, cancel} => {
+                self.spawn_walk(id, job, cancel);
+                Ok(())
+            },
+            HeavyWorkerAction::Measure{id, job, cancel} => {
+                self.spawn_measure(id, job, cancel);
+                Ok(())
+            },
+        }
+    }
+
+    /// Runs the job on its own thread so the listings are not blocked while it runs, its progress and result are sent to the UI
+    fn spawn_job<F>(&self, id: usize, cancel: Arc<AtomicBool>, job: F)
+    where F: FnOnce(&FsHeavyService, &mut JobProgress) -> Result<String, HeavyServiceError> + Send + 'static {
+        let output_channel = self.output_channel.clone();
+        thread::spawn(move || {
+            let mut progress = JobProgress { id, cancel, done: 0, total: 0, reported: None, output_channel: output_channel.clone() };
+            let response = match job(&FsHeavyService::new(), &mut progress) {
+                Ok(summary) => Ok(HeavyWorkerResponse::JobFinished(id, summary)),
+                Err(e) => Err(HeavyWorkerError::JobFailed(id, e)),
+            };
+            let _ = output_channel.send(response);
+        });
+    }
+
+    /// Walks the folder on its own thread, the files are sent to the UI by batches as they are found
+    fn spawn_walk(&self, id: usize, job: WalkJob, cancel: Arc<AtomicBool>) {
+        let output_channel = self.output_channel.clone();
+        thread::spawn(move || {
+            let mut sink = |files| {
+                let _ = output_channel.send(Ok(HeavyWorkerResponse::Walked{id, files}));
+            };
+            let response = match FsHeavyService::new().walk(&job, &|| cancel.load(Ordering::Relaxed), &mut sink) {
+                Ok(found) => Ok(HeavyWorkerResponse::WalkFinished(id, found)),
+                Err(e) => Err(HeavyWorkerError::WalkFailed(id, e)),
+            };
+            let _ = output_channel.send(response);
+        });
+    }
+
+    /// Measures the folders on their own thread, the size of each one is sent to the UI as soon as it's known
+    fn spawn_measure(&self, id: usize, job: MeasureJob, cancel: Arc<AtomicBool>) {
+        let output_channel = self.output_channel.clone();
+        thread::spawn(move || {
+            let service = FsHeavyService::new();
+            for path in job.paths {
+                let response = match service.measure(&path, job.cross_filesystems, &|| cancel.load(Ordering::Relaxed)) {
+                    Ok(usage) => Ok(HeavyWorkerResponse::Measured{id, path, usage}),
+                    Err(HeavyServiceError::Cancelled) => break,
+                    Err(e) => Err(HeavyWorkerError::MeasureFailed(id, path, e)),
+                };
+                let _ = output_channel.send(response);
+            }
+            let _ = output_channel.send(Ok(HeavyWorkerResponse::MeasureFinished(id)));
+        });
+    }
+
+    fn list_archive(&mut self, path: PathBuf) -> Result<(), HeavyWorkerError> {
+        match self.service.list_archive(&path) {
+            Ok(response) => {
+                self.output_channel.send(Ok(HeavyWorkerResponse::Listed(response, path)))?;
+                Ok(())
+            },
+            Err(e) => {
+                self.output_channel.send(Err(HeavyWorkerError::ListFailed(e, path)))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn index_archive(&mut self, path: PathBuf) -> Result<(), HeavyWorkerError> {
+        match self.service.index_archive(&path) {
+            Ok(source) => {
+                self.output_channel.send(Ok(HeavyWorkerResponse::Indexed(source)))?;
+                Ok(())
+            },
+            Err(e) => {
+                self.output_channel.send(Err(HeavyWorkerError::IndexFailed(e, path)))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Progress of a job sent to the UI each time its percentage changes
+struct JobProgress {
+    id: usize,
+    cancel: Arc<AtomicBool>,
+    done: u64,
+    total: u64,
+    reported: Option<u64>,
+    output_channel: mpsc::Sender<Result<HeavyWorkerResponse, HeavyWorkerError>>,
+}
+
+impl Progress for JobProgress {
+    fn set_total(&mut self, total: u64) {
+        self.total = total;
+        let _ = self.output_channel.send(Ok(HeavyWorkerResponse::JobProgress{id: self.id, done: 0, total}));
+    }
+
+    fn advance(&mut self, bytes: u64) -> io::Result<()> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::other(Cancelled));
+        }
+        self.done += bytes;
+        let percent = (self.done * 100).checked_div(self.total).unwrap_or(100);
+        if self.reported != Some(percent) {
+            self.reported = Some(percent);
+            let _ = self.output_channel.send(Ok(HeavyWorkerResponse::JobProgress{id: self.id, done: self.done, total: self.total}));
+        }
+        Ok(())
+    }
+}
+
+pub enum HeavyWorkerMessage {
+    WorkerAction(HeavyWorkerAction),
+    Shutdown,
+}
+
+pub enum HeavyWorkerAction {
+    ListArchive(PathBuf),
+    // lists the entries of an archive to browse it, the light worker lists its folders from them
+    IndexArchive(PathBuf),
+    Extract{id: usize, job: ExtractJob, cancel: Arc<AtomicBool>},
+    Compress{id: usize, job: CompressJob, cancel: Arc<AtomicBool>},
+    Transfer{id: usize, job: TransferJob, cancel: Arc<AtomicBool>},
+    Walk{id: usize, job: WalkJob, cancel: Arc<AtomicBool>},
+    Measure{id: usize, job: MeasureJob, cancel: Arc<AtomicBool>},
+}
+
+pub enum HeavyWorkerResponse {
+    Listed(Preview, PathBuf),
+    Indexed(ArchiveSource),
+    JobProgress{id: usize, done: u64, total: u64},
+    JobFinished(usize, String),
+    // batch of files found by a walk
+    Walked{id: usize, files: Vec<File>},
+    // the walk ended with the number of files found
+    WalkFinished(usize, usize),
+    // total size of one of the measured folders
+    Measured{id: usize, path: PathBuf, usage: DiskUsage},
+    // all the folders of the measure are done or it was cancelled
+    MeasureFinished(usize),
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum HeavyWorkerError {
+    #[error("Error listing archive: {0}")]
+    ListFailed(HeavyServiceError, PathBuf),
+    #[error("Error indexing archive: {0}")]
+    IndexFailed(HeavyServiceError, PathBuf),
+    #[error("Job failed: {1}")]
+    JobFailed(usize, HeavyServiceError),
+    #[error("Recursive listing failed: {1}")]
+    WalkFailed(usize, HeavyServiceError),
+    #[error("Measure failed: {2}")]
+    MeasureFailed(usize, PathBuf, HeavyServiceError),
+    #[error("Error receiving message: UI is dead")]
+    ReceiveMessageFailed,
+    #[error("Error sending response: UI is dead")]
+    SendResponseFailed,
+}
+
+impl From<SendError<Result<HeavyWorkerResponse, HeavyWorkerError>>> for HeavyWorkerError {
+    fn from(_: SendError<Result<HeavyWorkerResponse, HeavyWorkerError>>) -> Self {
+        HeavyWorkerError::SendResponseFailed
+    }
+}
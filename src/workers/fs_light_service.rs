@@ -1,31 +1,251 @@
 use thiserror::Error;
-use std::path::PathBuf;
-use std::fs;
-use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::fs::{self, DirEntry};
 use crate::file::File;
-use std::fs::OpenOptions;
-use std::io::Read;
-pub struct FsLightService;
+use crate::archive;
+use crate::workers::source::{Source, LocalSource, ArchiveSource};
+use crate::workers::listing_cache::{self, ListingCache, CacheStats, MAX_CACHE_BYTES};
+use crate::preview::{Preview, Highlighter};
+use crate::preview::encoding;
+use crate::preview::ImagePreview;
+use crate::preview::image::{self, ColorMode};
+use encoding_rs::Encoding;
+
+// Max number of bytes read from a file to build its preview
+pub static PREVIEW_READ_LIMIT: u64 = 1024 * 1024;
+// Max number of bytes read from an image to decode it
+static IMAGE_READ_LIMIT: u64 = 64 * 1024 * 1024;
+// Number of entries whose metadata is read for each batch of a folder load
+static LOAD_BATCH_SIZE: usize = 1024;
+
+pub struct FsLightService {
+    highlighter: Highlighter,
+    color_mode: ColorMode,
+    local: LocalSource,
+    // index of the last archive browsed, built by the heavy worker and kept until the archive changes
+    archive: Option<ArchiveSource>,
+    cache: ListingCache,
+}
 
 impl FsLightService {
     pub fn new() -> Self {
-        Self {}
+        Self { highlighter: Highlighter::new(), color_mode: ColorMode::detect(), local: LocalSource, archive: None, cache: ListingCache::new(MAX_CACHE_BYTES) }
     }
 
-    pub fn load(&self, path: &PathBuf) -> Result<Vec<File> , LightServiceError> {
-        let files : Vec<File> = fs::read_dir(&path)?.filter_map(|entry : Result<DirEntry, std::io::Error>| File::try_from(entry.ok()?).ok()).collect::<Vec<File>>();
+    pub fn load(&mut self, path: &Path, options: ListingOptions) -> Result<Vec<File> , LightServiceError> {
+        let modified = folder_modified(path);
+        if let Some(files) = modified.and_then(|modified| self.cache.get(path, modified, options.show_hidden)) {
+            return Ok(files);
+        }
+        let (source, inner) = self.source(path, true)?;
+        let mut files : Vec<File> = source.list(&inner)?
+            .into_iter()
+            .filter(|file| options.show_hidden || !file.is_hidden())
+            .collect::<Vec<File>>();
+        sort_files(&mut files);
+        if let Some(modified) = modified {
+            self.cache.insert(path.to_path_buf(), files.clone(), modified, options.show_hidden);
+        }
         Ok(files)
     }
 
-    pub fn read(&self, path: &PathBuf) -> Result<String, LightServiceError> {
-        let mut file = OpenOptions::new().read(true).open(path)?;
-        let mut content = Vec::with_capacity(1024);
-        file.read_to_end(&mut content)?;
-        match String::from_utf8(content) {
-            Ok(content) => Ok(content),
-            Err(error) => Err(LightServiceError::ReadFailed(format!("Invalid UTF-8 encoding ({})", error.to_string()))),
+    /// Starts loading the folder by batches, the entries of a local folder are sorted on their names and types before their metadata is read
+    /// so the batches come in the order of [`FsLightService::load`], a cached or an archive folder comes in one batch.
+    /// Without `cached` the folder is listed from the disk and its cached listing is replaced
+    pub fn loader(&mut self, path: &Path, options: ListingOptions, cached: bool) -> Result<FolderLoader, LightServiceError> {
+        let modified = folder_modified(path);
+        if let Some(files) = modified.filter(|_| cached).and_then(|modified| self.cache.get(path, modified, options.show_hidden)) {
+            return Ok(FolderLoader { entries: Vec::new().into_iter(), files: Some(files), fill: None, cached: true });
+        }
+        if archive::split_virtual_path(path).is_some() {
+            return Ok(FolderLoader { entries: Vec::new().into_iter(), files: Some(self.load(path, options)?), fill: None, cached: false });
+        }
+        let mut entries = fs::read_dir(path)?
+            .filter_map(Result::ok)
+            .filter(|entry| options.show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+            .collect::<Vec<DirEntry>>();
+        entries.sort_by_cached_key(|entry| (!entry.file_type().is_ok_and(|file_type| file_type.is_dir()), entry.file_name().to_string_lossy().to_lowercase()));
+        let fill = modified.map(|modified| CacheFill { path: path.to_path_buf(), modified, show_hidden: options.show_hidden, files: Vec::new(), bytes: 0, max_bytes: MAX_CACHE_BYTES });
+        Ok(FolderLoader { entries: entries.into_iter(), files: None, fill, cached: false })
+    }
+
+    /// Caches the listing of the folder once all its batches were taken
+    pub fn finish(&mut self, loader: FolderLoader) {
+        if let Some(CacheFill { path, modified, show_hidden, files, .. }) = loader.fill.filter(|_| loader.entries.len() == 0) {
+            self.cache.insert(path, files, modified, show_hidden);
         }
     }
+
+    /// Keeps the index of the archive for the loads of its folders
+    pub fn set_archive(&mut self, source: ArchiveSource) {
+        self.archive = Some(source);
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Lists the entries of a folder to preview them, the listing follows the same rules as [`FsLightService::load`]
+    pub fn list(&mut self, path: &Path, options: ListingOptions) -> Result<Preview, LightServiceError> {
+        let entries = self.load(path, options)?;
+        Ok(Preview::directory(entries))
+    }
+
+    /// Follows a symbolic link hop by hop to preview the chain of intermediate links and the final target
+    pub fn resolve_link(&self, path: &Path) -> Result<Preview, LightServiceError> {
+        let target = fs::read_link(path)?;
+        let mut chain = vec![path.to_path_buf()];
+        let mut current = path.to_path_buf();
+        while chain.len() <= MAX_LINK_HOPS {
+            let next = match fs::read_link(&current) {
+                // relative targets are relative to the folder containing the link
+                Ok(next) => current.parent().map(|parent| parent.join(&next)).unwrap_or(next),
+                Err(_) => break,
+            };
+            chain.push(next.clone());
+            match fs::symlink_metadata(&next) {
+                Ok(metadata) if metadata.file_type().is_symlink() => current = next,
+                _ => break,
+            }
+        }
+        let canonical = fs::canonicalize(path).ok();
+        Ok(Preview::Link { target, is_dead: canonical.is_none(), chain, canonical })
+    }
+
+    /// Reads the start of the file to preview it, images are decoded entirely and downscaled to the preview size,
+    /// for texts the encoding is detected unless one is forced
+    pub fn read(&mut self, path: &Path, options: ReadOptions) -> Result<Preview, LightServiceError> {
+        let (source, source_path) = self.source(path, false)?;
+        let bytes = source.read(&source_path, PREVIEW_READ_LIMIT)?;
+        if let Some(format) = image::detect_format(&bytes) {
+            let bytes = source.read(&source_path, IMAGE_READ_LIMIT)?;
+            let preview = ImagePreview::new(&bytes, format, options.area, self.color_mode).map_err(LightServiceError::ReadFailed)?;
+            return Ok(Preview::Image(Box::new(preview)));
+        }
+        let encoding = match options.encoding.or_else(|| encoding::detect(&bytes)) {
+            Some(encoding) => encoding,
+            None => return Err(LightServiceError::ReadFailed("Binary file".to_string())),
+        };
+        let truncated = bytes.len() as u64 == PREVIEW_READ_LIMIT;
+        // a followed file too big to be read entirely is read from its end, without its first line that may be cut
+        let tail = truncated && options.tail;
+        let bytes = match tail {
+            true => {
+                let mut bytes = source.read_tail(&source_path, PREVIEW_READ_LIMIT)?;
+                let start = bytes.iter().position(|byte| *byte == b'\n').map_or(0, |newline| newline + 1);
+                bytes.drain(..start);
+                bytes
+            },
+            false => bytes,
+        };
+        let mut content = encoding::decode(&bytes, encoding);
+        // the read limit may have cut the last character in half, its replacement character is dropped
+        if truncated && !tail && content.ends_with('\u{FFFD}') {
+            content.pop();
+        }
+        Ok(Preview::text(path, &content, Some(encoding), truncated, &self.highlighter))
+    }
+}
+
+// private methods
+impl FsLightService {
+    /// Returns the source holding the path and the path relative to it, the archive of a virtual path has to be indexed by the heavy worker first.
+    /// A listing needs the index of the archive as it is on the disk, a file is read even if the archive changed since
+    fn source(&self, path: &Path, listing: bool) -> Result<(&dyn Source, PathBuf), LightServiceError> {
+        let (archive, inner) = match archive::split_virtual_path(path) {
+            Some(split) => split,
+            None => return Ok((&self.local, path.to_path_buf())),
+        };
+        match &self.archive {
+            Some(source) if source.reads(&archive) && (!listing || source.is_current(&archive)) => Ok((source, inner)),
+            _ => Err(LightServiceError::NotIndexed(path.to_path_buf())),
+        }
+    }
+}
+
+/// Folder being loaded by batches, the metadata of an entry is only read when its batch is taken
+pub struct FolderLoader {
+    entries: std::vec::IntoIter<DirEntry>,
+    // files already listed, handed as the only batch
+    files: Option<Vec<File>>,
+    // listing gathered from the batches to be cached, given up when it outgrows the cache
+    fill: Option<CacheFill>,
+    // the files come from the listing cache
+    cached: bool,
+}
+
+struct CacheFill {
+    path: PathBuf,
+    modified: SystemTime,
+    show_hidden: bool,
+    files: Vec<File>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl FolderLoader {
+    pub fn is_cached(&self) -> bool {
+        self.cached
+    }
+
+    /// Returns the next batch of files, None once the folder is fully loaded
+    pub fn next_batch(&mut self) -> Option<Vec<File>> {
+        if let Some(files) = self.files.take() {
+            return Some(files);
+        }
+        if self.entries.len() == 0 {
+            return None;
+        }
+        let batch = self.entries.by_ref().take(LOAD_BATCH_SIZE).filter_map(|entry| File::try_from(entry).ok()).collect::<Vec<File>>();
+        if let Some(fill) = &mut self.fill {
+            fill.bytes += listing_cache::estimated_size(&batch);
+            match fill.bytes > fill.max_bytes {
+                true => self.fill = None,
+                false => fill.files.extend(batch.iter().cloned()),
+            }
+        }
+        Some(batch)
+    }
+}
+
+// Max number of links followed when resolving a symbolic link, same as the linux kernel
+static MAX_LINK_HOPS: usize = 40;
+
+/// Rules applied when listing the entries of a folder
+#[derive(Debug, Copy, Clone)]
+pub struct ListingOptions {
+    pub show_hidden: bool,
+}
+
+impl Default for ListingOptions {
+    fn default() -> Self {
+        Self { show_hidden: true }
+    }
+}
+
+/// Parameters of a file preview
+#[derive(Debug, Copy, Clone)]
+pub struct ReadOptions {
+    // encoding forced by the user, detected when None
+    pub encoding: Option<&'static Encoding>,
+    // cells width and height of the preview section
+    pub area: (u16, u16),
+    // the end of the file is read when it's over the read limit
+    pub tail: bool,
+}
+
+/// Returns the modification time of a local folder, used to know if its cached listing is still valid
+fn folder_modified(path: &Path) -> Option<SystemTime> {
+    if archive::split_virtual_path(path).is_some() {
+        return None;
+    }
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Sorts the files with the folders first, then by case insensitive name
+fn sort_files(files: &mut [File]) {
+    files.sort_by_cached_key(|file| (!file.is_folder(), file.name().to_lowercase()));
 }
 
 #[derive(Error, Debug)]
@@ -34,15 +254,60 @@ pub enum LightServiceError {
     LoadFailed(String),
     #[error("{0}")]
     ReadFailed(String),
+    // the folder is in an archive whose index has to be built by the heavy worker
+    #[error("{} is not indexed yet", .0.display())]
+    NotIndexed(PathBuf),
 }
 
 impl From<std::io::Error> for LightServiceError {
     fn from(error: std::io::Error) -> Self {
         match error.kind() {
-            std::io::ErrorKind::NotFound => LightServiceError::LoadFailed(format!("Path not found ({})", error.to_string())),
-            std::io::ErrorKind::PermissionDenied => LightServiceError::LoadFailed(format!("Permission denied ({})", error.to_string())),
-            std::io::ErrorKind::NotADirectory => LightServiceError::LoadFailed(format!("Path is not a directory ({})", error.to_string())),
+            std::io::ErrorKind::NotFound => LightServiceError::LoadFailed(format!("Path not found ({})", error)),
+            std::io::ErrorKind::PermissionDenied => LightServiceError::LoadFailed(format!("Permission denied ({})", error)),
+            std::io::ErrorKind::NotADirectory => LightServiceError::LoadFailed(format!("Path is not a directory ({})", error)),
             _ => LightServiceError::LoadFailed(error.to_string()),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+    use crate::preview::Preview;
+    use crate::utility::temp::TempDir;
+    use super::{FsLightService, MAX_LINK_HOPS};
+
+    /// Returns the chain, the canonical path and whether the link is dead
+    fn resolve(service: &FsLightService, path: &Path) -> (Vec<PathBuf>, Option<PathBuf>, bool) {
+        match service.resolve_link(path).unwrap() {
+            Preview::Link { chain, canonical, is_dead, .. } => (chain, canonical, is_dead),
+            _ => panic!("not a link preview"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let root = TempDir::new("links");
+        let service = FsLightService::new();
+        fs::create_dir(root.join("d")).unwrap();
+        fs::write(root.join("c.txt"), "c").unwrap();
+        // the relative targets are resolved from the folder of their link
+        symlink("../c.txt", root.join("d/b")).unwrap();
+        symlink("d/b", root.join("a")).unwrap();
+        let (chain, canonical, is_dead) = resolve(&service, &root.join("a"));
+        assert_eq!(chain, vec![root.join("a"), root.join("d/b"), root.join("d/../c.txt")]);
+        assert_eq!((canonical, is_dead), (Some(fs::canonicalize(root.join("c.txt")).unwrap()), false));
+
+        symlink("missing", root.join("dead")).unwrap();
+        assert_eq!(resolve(&service, &root.join("dead")), (vec![root.join("dead"), root.join("missing")], None, true));
+
+        // a loop is followed up to the max number of hops
+        symlink("y", root.join("x")).unwrap();
+        symlink("x", root.join("y")).unwrap();
+        let (chain, canonical, is_dead) = resolve(&service, &root.join("x"));
+        assert_eq!(chain.len(), MAX_LINK_HOPS + 1);
+        assert_eq!(&chain[..3], &[root.join("x"), root.join("y"), root.join("x")]);
+        assert_eq!((canonical, is_dead), (None, true));
+    }
+}
@@ -1,9 +1,15 @@
 use std::sync::mpsc;
 use std::path::PathBuf;
+use std::collections::HashMap;
 use crate::file::File;
 use thiserror::Error;
 use crate::workers::LightServiceError;
 use crate::workers::FsLightService;
+use crate::workers::ListingOptions;
+use crate::workers::CacheStats;
+use crate::workers::ArchiveSource;
+use crate::preview::Preview;
+use crate::workers::ReadOptions;
 use std::sync::mpsc::SendError;
 
 pub struct FsLightWorker {
@@ -11,16 +17,18 @@ pub struct FsLightWorker {
     input_channel: mpsc::Receiver<LightWorkerMessage>,
     output_channel: mpsc::Sender<Result<LightWorkerResponse, LightWorkerError>>,
     service: FsLightService,
+    // message that interrupted a folder load, handled before the next one is received
+    pending: Option<LightWorkerMessage>,
 }
 
 impl FsLightWorker {
     pub fn new(sync_id: usize, input_channel: mpsc::Receiver<LightWorkerMessage>, output_channel: mpsc::Sender<Result<LightWorkerResponse, LightWorkerError>>) -> Self {
-        Self { sync_id, input_channel, output_channel, service: FsLightService::new() }
+        Self { sync_id, input_channel, output_channel, service: FsLightService::new(), pending: None }
     }
 
     pub fn run(&mut self) -> Result<(), LightWorkerError> {
         loop {
-            match self.input_channel.recv() {
+            match self.pending.take().map_or_else(|| self.input_channel.recv(), Ok) {
                 Ok(message) => {
                     match message {
                         LightWorkerMessage::Shutdown => break Ok(()),
@@ -28,10 +36,11 @@ impl FsLightWorker {
                             if sync_id != self.sync_id {
                                 continue;
                             }
-                            self.handle_action(action)?;
-                            self.sync_id = (self.sync_id + 1) % usize::MAX;
+                            // an interrupted load has no response, the action that interrupted it is handled with the same id
+                            if self.handle_action(action)? {
+                                self.sync_id = (self.sync_id + 1) % usize::MAX;
+                            }
                         }
-                        
                     }
                 },
                 Err(_) => return Err(LightWorkerError::ReceiveMessageFailed),
@@ -42,29 +51,135 @@ impl FsLightWorker {
 }
 
 impl FsLightWorker {
-    fn handle_action(&mut self, action: LightWorkerAction) -> Result<(), LightWorkerError> {
+    /// Handles the action, returns false when it was interrupted before sending its response
+    fn handle_action(&mut self, action: LightWorkerAction) -> Result<bool, LightWorkerError> {
         match action {
-            LightWorkerAction::Load(path) => {
-                self.load(path)
+            // a refresh is not in lockstep with the UI, the actions sent during it keep their id
+            LightWorkerAction::Load(request) if request.refresh => {
+                self.load(request).map(|_| false)
+            },
+            LightWorkerAction::Load(request) => {
+                self.load(request)
+            },
+            LightWorkerAction::Expand(path, options) => {
+                self.expand(path, options).map(|_| true)
+            },
+            LightWorkerAction::Read(path, options) => {
+                self.read(path, options).map(|_| true)
             },
-            LightWorkerAction::Read(path) => {
-                self.read(path)
+            LightWorkerAction::List(path, options) => {
+                self.list(path, options).map(|_| true)
+            },
+            LightWorkerAction::ResolveLink(path) => {
+                self.resolve_link(path).map(|_| true)
             },
         }
     }
 
-    fn load(&mut self, path: PathBuf) -> Result<(), LightWorkerError> {
-        match self.service.load(&path) {
+    /// Loads the folder with the extra listings of the request, the parent and the subfolders that can't be listed are left out.
+    /// A big folder is sent by batches before its listing, the load is interrupted by a newer one. A refresh that fails leaves the listing as is
+    fn load(&mut self, request: LoadRequest) -> Result<bool, LightWorkerError> {
+        let LoadRequest { path, options, with_parent, expanded, cached, archive, refresh } = request;
+        if let Some(source) = archive {
+            self.service.set_archive(source);
+        }
+        let mut loader = match self.service.loader(&path, options, cached) {
+            Ok(loader) => loader,
+            Err(_) if refresh => return Ok(true),
+            Err(e) => {
+                self.output_channel.send(Err(LightWorkerError::LoadFailed(e)))?;
+                return Ok(true);
+            },
+        };
+        let from_cache = loader.is_cached();
+        // the last batch is held back to be sent in the listing
+        let mut files = loader.next_batch().unwrap_or_default();
+        let mut streamed = false;
+        while let Some(batch) = loader.next_batch() {
+            let files = std::mem::replace(&mut files, batch);
+            self.output_channel.send(Ok(LightWorkerResponse::Loading{path: path.clone(), files, first: !streamed}))?;
+            streamed = true;
+            if !self.handle_queued_actions()? {
+                return Ok(false);
+            }
+        }
+        self.service.finish(loader);
+        let parent = match path.parent() {
+            Some(parent) if with_parent => self.service.load(parent, options).ok(),
+            _ => None,
+        };
+        let subtrees = expanded.into_iter()
+            .filter_map(|folder| self.service.load(&path.join(&folder), options).ok().map(|files| (folder, files)))
+            .collect();
+        self.output_channel.send(Ok(LightWorkerResponse::Loaded(Listing { path, files, parent, subtrees, streamed, refresh, from_cache, cache_stats: self.service.cache_stats() })))?;
+        Ok(true)
+    }
+
+    /// Handles the actions sent while a folder loads so the previews are not delayed by it,
+    /// returns false when a newer load or the shutdown has to interrupt it
+    fn handle_queued_actions(&mut self) -> Result<bool, LightWorkerError> {
+        while let Ok(message) = self.input_channel.try_recv() {
+            match message {
+                LightWorkerMessage::WorkerAction{sync_id, action: LightWorkerAction::Load(request)} if sync_id == self.sync_id => {
+                    self.pending = Some(LightWorkerMessage::WorkerAction{sync_id, action: LightWorkerAction::Load(request)});
+                    return Ok(false);
+                },
+                LightWorkerMessage::WorkerAction{sync_id, action} if sync_id == self.sync_id => {
+                    if self.handle_action(action)? {
+                        self.sync_id = (self.sync_id + 1) % usize::MAX;
+                    }
+                },
+                LightWorkerMessage::WorkerAction{..} => {},
+                LightWorkerMessage::Shutdown => {
+                    self.pending = Some(LightWorkerMessage::Shutdown);
+                    return Ok(false);
+                },
+            }
+        }
+        Ok(true)
+    }
+
+    fn expand(&mut self, path: PathBuf, options: ListingOptions) -> Result<(), LightWorkerError> {
+        match self.service.load(&path, options) {
             Ok(response) => {
-                self.output_channel.send(Ok(LightWorkerResponse::Loaded(response, path)))?;
+                self.output_channel.send(Ok(LightWorkerResponse::Expanded(response, path)))?;
+                Ok(())
+            },
+            Err(e) => {
+                self.output_channel.send(Err(LightWorkerError::LoadFailed(e)))?;
                 Ok(())
             },
-            Err(e) => Err(LightWorkerError::LoadFailed(e)),
         }
     }
 
-    fn read(&mut self, path: PathBuf) -> Result<(), LightWorkerError> {
-        match self.service.read(&path) {
+    fn read(&mut self, path: PathBuf, options: ReadOptions) -> Result<(), LightWorkerError> {
+        match self.service.read(&path, options) {
+            Ok(response) => {
+                self.output_channel.send(Ok(LightWorkerResponse::Read(response, path)))?;
+                Ok(())
+            },
+            Err(e) => {
+                self.output_channel.send(Err(LightWorkerError::ReadFailed(e)))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn list(&mut self, path: PathBuf, options: ListingOptions) -> Result<(), LightWorkerError> {
+        match self.service.list(&path, options) {
+            Ok(response) => {
+                self.output_channel.send(Ok(LightWorkerResponse::Read(response, path)))?;
+                Ok(())
+            },
+            Err(e) => {
+                self.output_channel.send(Err(LightWorkerError::ReadFailed(e)))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn resolve_link(&mut self, path: PathBuf) -> Result<(), LightWorkerError> {
+        match self.service.resolve_link(&path) {
             Ok(response) => {
                 self.output_channel.send(Ok(LightWorkerResponse::Read(response, path)))?;
                 Ok(())
@@ -83,16 +198,55 @@ pub enum LightWorkerMessage {
 }
 
 pub enum LightWorkerAction {
-    Load(PathBuf),
-    Read(PathBuf),
+    Load(LoadRequest),
+    // lists a subfolder to show it inline in the tree view
+    Expand(PathBuf, ListingOptions),
+    Read(PathBuf, ReadOptions),
+    List(PathBuf, ListingOptions),
+    ResolveLink(PathBuf),
 }
 
 pub enum LightWorkerResponse {
-    Loaded(Vec<File>, PathBuf),
-    Read(String, PathBuf)
+    // batch of a big folder sent before its listing, the first one starts the load
+    Loading{path: PathBuf, files: Vec<File>, first: bool},
+    Loaded(Listing),
+    Expanded(Vec<File>, PathBuf),
+    Read(Preview, PathBuf)
+}
+
+/// Folder to load with the other listings displayed along it
+pub struct LoadRequest {
+    pub path: PathBuf,
+    pub options: ListingOptions,
+    // the parent folder is listed for the miller columns
+    pub with_parent: bool,
+    // subfolders expanded in the tree view, relative to the path
+    pub expanded: Vec<PathBuf>,
+    // the cached listing can be used if the folder didn't change, a refresh lists it from the disk
+    pub cached: bool,
+    // index of the archive of the folder built by the heavy worker, the service keeps it for the next loads
+    pub archive: Option<ArchiveSource>,
+    // reload sent by the watcher, its response doesn't count in the lockstep so the user actions sent meanwhile are not dropped
+    pub refresh: bool,
+}
+
+/// Loaded folder with the listings requested along it
+pub struct Listing {
+    pub path: PathBuf,
+    pub files: Vec<File>,
+    pub parent: Option<Vec<File>>,
+    // listings of the expanded subfolders by path relative to the folder
+    pub subtrees: HashMap<PathBuf, Vec<File>>,
+    // the first files were sent by Loading batches, the listing holds the last ones
+    pub streamed: bool,
+    pub refresh: bool,
+    // the files come from the listing cache, the folder may hold files modified in place since
+    pub from_cache: bool,
+    pub cache_stats: CacheStats,
 }
 
-#[derive(Error, Debug)] 
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum LightWorkerError {
     #[error("Error loading files: {0}")]
     LoadFailed(LightServiceError),
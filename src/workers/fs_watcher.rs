@@ -0,0 +1,188 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::io;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+// Time waited for a message before the inotify events are read again
+static POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Quiet time after the last event of a burst before the change is sent
+static DEBOUNCE_DELAY: Duration = Duration::from_millis(250);
+// Max time a change is held during a burst that doesn't stop, so a growing file is still refreshed
+static MAX_DEBOUNCE_DELAY: Duration = Duration::from_secs(1);
+
+/// Watches the current folder and the previewed file with inotify, the bursts of events are debounced into one change sent to the UI
+pub struct FsWatcher {
+    input_channel: mpsc::Receiver<WatcherMessage>,
+    output_channel: mpsc::Sender<WatcherResponse>,
+    inotify: Inotify,
+    folder: Option<Watch>,
+    file: Option<Watch>,
+}
+
+/// Watched path with the events of the burst not sent yet
+struct Watch {
+    path: PathBuf,
+    descriptor: WatchDescriptor,
+    // time of the first and of the last event of the burst
+    burst: Option<(Instant, Instant)>,
+}
+
+impl FsWatcher {
+    pub fn new(input_channel: mpsc::Receiver<WatcherMessage>, output_channel: mpsc::Sender<WatcherResponse>) -> io::Result<Self> {
+        Ok(Self { input_channel, output_channel, inotify: Inotify::init()?, folder: None, file: None })
+    }
+
+    pub fn run(&mut self) {
+        loop {
+            match self.input_channel.recv_timeout(POLL_INTERVAL) {
+                Ok(WatcherMessage::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+                Ok(WatcherMessage::Watch{folder, file}) => self.watch(folder, file),
+                Err(RecvTimeoutError::Timeout) => {},
+            }
+            self.read_events(Instant::now());
+            if !self.send_settled(Instant::now()) {
+                return;
+            }
+        }
+    }
+}
+
+// private methods
+impl FsWatcher {
+    fn watch(&mut self, folder: Option<PathBuf>, file: Option<PathBuf>) {
+        let (previous_folder, previous_file) = (self.folder.take(), self.file.take());
+        // the folder is refreshed when its entries change, a file written in it is only seen by a reload
+        let entries = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_FROM | WatchMask::MOVED_TO | WatchMask::ATTRIB
+            | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+        self.folder = self.replace(previous_folder, folder, entries);
+        self.file = self.replace(previous_file, file, entries | WatchMask::MODIFY | WatchMask::CLOSE_WRITE);
+    }
+
+    /// Sends a change for each watch whose burst settled, returns false once the UI is gone
+    fn send_settled(&mut self, now: Instant) -> bool {
+        for watch in [&mut self.folder, &mut self.file].into_iter().flatten() {
+            if watch.burst.is_some_and(|burst| is_settled(burst, now)) {
+                watch.burst = None;
+                if self.output_channel.send(WatcherResponse::Changed(watch.path.clone())).is_err() {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Moves the watch to the new path, the watch is kept as is when the path didn't change
+    fn replace(&mut self, watch: Option<Watch>, path: Option<PathBuf>, mask: WatchMask) -> Option<Watch> {
+        if watch.as_ref().map(|watch| &watch.path) == path.as_ref() {
+            return watch;
+        }
+        if let Some(watch) = watch {
+            // the watch is already gone if its path was removed
+            let _ = self.inotify.watches().remove(watch.descriptor);
+        }
+        let path = path?;
+        let descriptor = self.inotify.watches().add(&path, mask).ok()?;
+        Some(Watch { path, descriptor, burst: None })
+    }
+
+    /// Reads the pending events without blocking and records them in the burst of their watch
+    fn read_events(&mut self, now: Instant) {
+        let mut buffer = [0; 4096];
+        loop {
+            let descriptors = match self.inotify.read_events(&mut buffer) {
+                Ok(events) => events.filter(|event| !event.mask.contains(EventMask::IGNORED)).map(|event| event.wd).collect::<Vec<WatchDescriptor>>(),
+                Err(_) => return,
+            };
+            if descriptors.is_empty() {
+                return;
+            }
+            for watch in [&mut self.folder, &mut self.file].into_iter().flatten() {
+                if descriptors.contains(&watch.descriptor) {
+                    watch.burst = Some(extend_burst(watch.burst, now));
+                }
+            }
+        }
+    }
+}
+
+/// Adds an event at `now` to the burst, the first event of a burst starts it
+fn extend_burst(burst: Option<(Instant, Instant)>, now: Instant) -> (Instant, Instant) {
+    (burst.map_or(now, |(first, _)| first), now)
+}
+
+/// Tells if the burst is over, either quiet since its last event or held for too long
+fn is_settled((first, last): (Instant, Instant), now: Instant) -> bool {
+    now - last >= DEBOUNCE_DELAY || now - first >= MAX_DEBOUNCE_DELAY
+}
+
+pub enum WatcherMessage {
+    // paths to watch, None stops watching
+    Watch{folder: Option<PathBuf>, file: Option<PathBuf>},
+    Shutdown,
+}
+
+pub enum WatcherResponse {
+    // the folder or the file changed on the disk
+    Changed(PathBuf),
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::mpsc::{self, TryRecvError};
+    use std::time::{Duration, Instant};
+    use crate::utility::temp::TempDir;
+    use super::{FsWatcher, WatcherResponse, DEBOUNCE_DELAY, MAX_DEBOUNCE_DELAY, extend_burst, is_settled};
+
+    #[test]
+    fn test_debounce() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut burst = extend_burst(None, at(0));
+        assert_eq!(burst, (at(0), at(0)));
+        assert!(!is_settled(burst, at(200)));
+        assert!(is_settled(burst, at(0) + DEBOUNCE_DELAY));
+        // the events keep the burst open until it's held for the max delay
+        for millis in (100..=900).step_by(200) {
+            burst = extend_burst(Some(burst), at(millis));
+        }
+        assert_eq!(burst, (at(0), at(900)));
+        assert!(!is_settled(burst, at(999)));
+        assert!(is_settled(burst, at(0) + MAX_DEBOUNCE_DELAY));
+    }
+
+    /// Returns the paths of the changes sent once the bursts read are settled
+    fn changes(watcher: &mut FsWatcher, receiver: &mpsc::Receiver<WatcherResponse>) -> Vec<String> {
+        let now = Instant::now();
+        watcher.read_events(now);
+        assert!(watcher.send_settled(now));
+        assert_eq!(receiver.try_recv().err(), Some(TryRecvError::Empty));
+        assert!(watcher.send_settled(now + DEBOUNCE_DELAY));
+        receiver.try_iter().map(|WatcherResponse::Changed(path)| path.to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn test_watch() {
+        let (root, other) = (TempDir::new("watch"), TempDir::new("watch_other"));
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = FsWatcher::new(mpsc::channel().1, sender).unwrap();
+        let (folder, file) = (root.to_string_lossy().to_string(), root.join("a.txt"));
+        watcher.watch(Some(root.to_path_buf()), None);
+        // the events of a new file make a single change
+        fs::write(&file, "a").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        assert_eq!(changes(&mut watcher, &receiver), vec![folder.clone()]);
+        // a file written in the folder is ignored unless it's the watched file
+        fs::write(&file, "ab").unwrap();
+        assert_eq!(changes(&mut watcher, &receiver), Vec::<String>::new());
+        watcher.watch(Some(root.to_path_buf()), Some(file.clone()));
+        fs::write(&file, "abc").unwrap();
+        assert_eq!(changes(&mut watcher, &receiver), vec![file.to_string_lossy().to_string()]);
+        // the watch moves to the new folder
+        watcher.watch(Some(other.to_path_buf()), None);
+        fs::write(root.join("c.txt"), "c").unwrap();
+        fs::write(other.join("d.txt"), "d").unwrap();
+        assert_eq!(changes(&mut watcher, &receiver), vec![other.to_string_lossy().to_string()]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::file::{File, FileType, Size};
+
+// Memory bound of the cached listings
+pub static MAX_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Least recently used listings of the local folders by path, a listing is only valid while its folder keeps the modification time it had when listed.
+/// The folder time changes when an entry is added, removed or renamed, a file modified in place is seen by the refresh the UI sends after a cache hit
+pub struct ListingCache {
+    entries: HashMap<PathBuf, CachedListing>,
+    max_bytes: usize,
+    bytes: usize,
+    // incremented on each use, the entry used the longest time ago has the lowest
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+struct CachedListing {
+    files: Vec<File>,
+    modified: SystemTime,
+    show_hidden: bool,
+    bytes: usize,
+    used: u64,
+}
+
+impl ListingCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { entries: HashMap::new(), max_bytes, bytes: 0, clock: 0, hits: 0, misses: 0, evictions: 0 }
+    }
+
+    /// Returns the listing of the folder if it was cached with the same rule for the hidden files and the folder didn't change since
+    pub fn get(&mut self, path: &Path, modified: SystemTime, show_hidden: bool) -> Option<Vec<File>> {
+        let entry = self.entries.get_mut(path).filter(|entry| entry.modified == modified && entry.show_hidden == show_hidden);
+        match entry {
+            Some(entry) => {
+                self.clock += 1;
+                entry.used = self.clock;
+                self.hits += 1;
+                Some(entry.files.clone())
+            },
+            None => {
+                self.misses += 1;
+                None
+            },
+        }
+    }
+
+    /// Caches the listing of the folder in place of the previous one, the least recently used listings are evicted to stay under the memory bound.
+    /// A listing bigger than the bound is not cached
+    pub fn insert(&mut self, path: PathBuf, files: Vec<File>, modified: SystemTime, show_hidden: bool) {
+        self.remove(&path);
+        let bytes = estimated_size(&files);
+        if bytes > self.max_bytes {
+            return;
+        }
+        while self.bytes + bytes > self.max_bytes {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, entry)| entry.used).map(|(path, _)| path.clone()) else {
+                break;
+            };
+            self.remove(&oldest);
+            self.evictions += 1;
+        }
+        self.clock += 1;
+        self.bytes += bytes;
+        self.entries.insert(path, CachedListing { files, modified, show_hidden, bytes, used: self.clock });
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.bytes -= entry.bytes;
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats { folders: self.entries.len(), bytes: self.bytes, max_bytes: self.max_bytes, hits: self.hits, misses: self.misses, evictions: self.evictions }
+    }
+}
+
+/// Approximate memory used by the files of a listing
+pub fn estimated_size(files: &[File]) -> usize {
+    files.iter().map(|file| {
+        let target = match file.file_type() {
+            FileType::Link { target, .. } => target.as_os_str().len(),
+            _ => 0,
+        };
+        std::mem::size_of::<File>() + file.name().len() + target
+    }).sum()
+}
+
+/// Usage of the listing cache, shown for debugging
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CacheStats {
+    pub folders: usize,
+    pub bytes: usize,
+    pub max_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Listing cache: {} folders, {} of {}, {} hits, {} misses, {} evictions",
+            self.folders, Size::from(self.bytes as u64), Size::from(self.max_bytes as u64), self.hits, self.misses, self.evictions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use crate::file::{File, FileType};
+    use super::{ListingCache, estimated_size};
+
+    fn listing(names: &[&str]) -> Vec<File> {
+        names.iter().map(|name| File::new(name.to_string(), FileType::File, SystemTime::UNIX_EPOCH, 0)).collect()
+    }
+
+    #[test]
+    fn test_listing_cache() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        let files = listing(&["a", "b"]);
+        let mut cache = ListingCache::new(estimated_size(&files) * 2);
+        cache.insert(PathBuf::from("/x"), files, modified, false);
+        assert_eq!(cache.get(Path::new("/x"), modified, false).map(|files| files.len()), Some(2));
+        // a changed folder or another rule for the hidden files is a miss
+        assert!(cache.get(Path::new("/x"), modified + Duration::from_secs(1), false).is_none());
+        assert!(cache.get(Path::new("/x"), modified, true).is_none());
+
+        cache.insert(PathBuf::from("/y"), listing(&["c", "d"]), modified, false);
+        cache.get(Path::new("/x"), modified, false);
+        // the bound holds two listings, the least recently used one is evicted
+        cache.insert(PathBuf::from("/z"), listing(&["e", "f"]), modified, false);
+        assert!(cache.get(Path::new("/y"), modified, false).is_none());
+        assert!(cache.get(Path::new("/x"), modified, false).is_some());
+        // a listing bigger than the bound is not cached
+        cache.insert(PathBuf::from("/w"), listing(&["g", "h", "i", "j", "k"]), modified, false);
+        assert!(cache.get(Path::new("/w"), modified, false).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.folders, stats.hits, stats.misses, stats.evictions), (2, 3, 4, 1));
+        assert_eq!(stats.bytes, estimated_size(&listing(&["a", "b"])) * 2);
+    }
+}
@@ -4,7 +4,38 @@ pub use fs_light_worker::LightWorkerMessage;
 pub use fs_light_worker::LightWorkerAction;
 pub use fs_light_worker::LightWorkerResponse;
 pub use fs_light_worker::LightWorkerError;
+pub use fs_light_worker::LoadRequest;
+pub use fs_light_worker::Listing;
+
+mod source;
+pub use source::ArchiveSource;
+
+mod listing_cache;
+pub use listing_cache::CacheStats;
 
 mod fs_light_service;
 pub use fs_light_service::FsLightService;
-pub use fs_light_service::LightServiceError;
\ No newline at end of file
+pub use fs_light_service::LightServiceError;
+pub use fs_light_service::ListingOptions;
+pub use fs_light_service::ReadOptions;
+
+mod fs_watcher;
+pub use fs_watcher::FsWatcher;
+pub use fs_watcher::WatcherMessage;
+pub use fs_watcher::WatcherResponse;
+
+mod fs_heavy_worker;
+pub use fs_heavy_worker::FsHeavyWorker;
+pub use fs_heavy_worker::HeavyWorkerMessage;
+pub use fs_heavy_worker::HeavyWorkerAction;
+pub use fs_heavy_worker::HeavyWorkerResponse;
+pub use fs_heavy_worker::HeavyWorkerError;
+
+mod fs_heavy_service;
+pub use fs_heavy_service::FsHeavyService;
+pub use fs_heavy_service::HeavyServiceError;
+pub use fs_heavy_service::ExtractJob;
+pub use fs_heavy_service::CompressJob;
+pub use fs_heavy_service::TransferJob;
+pub use fs_heavy_service::WalkJob;
+pub use fs_heavy_service::MeasureJob;
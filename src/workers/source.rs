@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::DirEntry;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::archive::{self, ArchiveEntry, ArchiveKind};
+use crate::file::{File, FileType};
+
+/// Origin of the files browsed by the light service, paths given to a source are relative to it
+pub trait Source {
+    /// Lists the entries of the folder at the path
+    fn list(&self, path: &Path) -> io::Result<Vec<File>>;
+    /// Reads at most `limit` bytes of the file at the path
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>>;
+    /// Reads at most the last `limit` bytes of the file at the path, a source that can't seek reads the start
+    fn read_tail(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        self.read(path, limit)
+    }
+}
+
+/// Local filesystem, its paths are the absolute paths of the files
+pub struct LocalSource;
+
+impl Source for LocalSource {
+    fn list(&self, path: &Path) -> io::Result<Vec<File>> {
+        Ok(fs::read_dir(path)?
+            .filter_map(|entry : Result<DirEntry, std::io::Error>| File::try_from(entry.ok()?).ok())
+            .collect::<Vec<File>>())
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        let file = fs::File::open(path)?;
+        let mut bytes = Vec::with_capacity(1024);
+        file.take(limit).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_tail(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(path)?;
+        let length = file.metadata()?.len();
+        file.seek(SeekFrom::Start(length.saturating_sub(limit)))?;
+        let mut bytes = Vec::with_capacity(1024);
+        file.take(limit).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Archive browsed as a folder, its paths are relative to the archive root and its index is kept until the archive is modified.
+/// The index is built by the heavy worker so a big archive doesn't block the folder loads
+pub struct ArchiveSource {
+    archive: PathBuf,
+    kind: ArchiveKind,
+    modified: SystemTime,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl ArchiveSource {
+    pub fn open(archive: &Path) -> io::Result<Self> {
+        let kind = ArchiveKind::detect(archive).ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Unsupported archive format"))?;
+        let modified = fs::metadata(archive)?.modified()?;
+        let entries = archive::list(archive, kind)?;
+        Ok(Self { archive: archive.to_path_buf(), kind, modified, entries })
+    }
+
+    /// Returns true if the source reads the archive
+    pub fn reads(&self, archive: &Path) -> bool {
+        self.archive == archive
+    }
+
+    /// Returns true if the source reads the archive and the archive was not modified since its index was listed
+    pub fn is_current(&self, archive: &Path) -> bool {
+        self.reads(archive) && fs::metadata(archive).and_then(|metadata| metadata.modified()).is_ok_and(|modified| modified == self.modified)
+    }
+}
+
+impl Source for ArchiveSource {
+    /// Lists the direct children of the folder, the folders without an entry of their own are deduced from the paths of their content
+    fn list(&self, path: &Path) -> io::Result<Vec<File>> {
+        let mut children: HashMap<String, File> = HashMap::new();
+        for entry in &self.entries {
+            let relative = match Path::new(&entry.path).strip_prefix(path) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            let mut components = relative.components();
+            let name = match components.next() {
+                Some(name) => name.as_os_str().to_string_lossy().to_string(),
+                None => continue,
+            };
+            let modified = entry.modified.unwrap_or(self.modified);
+            if components.next().is_some() {
+                children.entry(name.clone()).or_insert_with(|| File::new(name, FileType::Folder, modified, 0));
+            }
+            else if entry.is_dir {
+                children.insert(name.clone(), File::new(name, FileType::Folder, modified, 0));
+            }
+            else {
+                children.insert(name.clone(), File::new(name, FileType::File, modified, entry.size));
+            }
+        }
+        let is_folder = path.as_os_str().is_empty() || self.entries.iter().any(|entry| entry.is_dir && Path::new(&entry.path) == path);
+        if children.is_empty() && !is_folder {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a folder of the archive", path.display())));
+        }
+        Ok(children.into_values().collect())
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        archive::read_entry(&self.archive, self.kind, path, limit)
+    }
+}